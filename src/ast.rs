@@ -1,9 +1,13 @@
 
+
+use std::any::Any;
+
+use crate::lexer::span::Span;
 use crate::lexer::token::TokenKind;
 
 
 
-pub trait Node : ToString {
+pub trait Node : ToString + AsAny {
 }
 
 pub trait Expression : Node {
@@ -12,7 +16,47 @@ pub trait Expression : Node {
 pub trait Statement : Node {
 }
 
-impl<T: ToString> Node for T {}
+impl<T: ToString + Any> Node for T {}
+
+/// The source text of an operator or keyword. Kinds without a fixed text, which the
+/// parser never puts in an operator position, print as their name rather than panic.
+fn operator_text(kind: TokenKind) -> String {
+    match kind.text() {
+        Some(text) => text.to_string(),
+        None => format!("{:?}", kind),
+    }
+}
+
+/// Lets the parser look at what kind of node it has, e.g. to check that an
+/// expression can be reinterpreted as an arrow function parameter.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any { self }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> { self }
+}
+
+impl dyn Expression {
+    pub fn is<T: Expression + Any>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    pub fn downcast_ref<T: Expression + Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Takes the node out of its box if it is a `T`, or gives the box back.
+    pub fn downcast<T: Expression + Any>(self: Box<Self>) -> Result<Box<T>, Box<dyn Expression>> {
+        if self.is::<T>() {
+            Ok(self.into_any().downcast().unwrap())
+        } else {
+            Err(self)
+        }
+    }
+}
 
 pub struct Program {
     pub statements: Vec<Box<dyn Statement>>,
@@ -59,15 +103,365 @@ impl ToString for NumberLiteral {
 
 impl Expression for NumberLiteral { }
 
-pub struct NoOpExpression;
+pub struct BooleanLiteral {
+    pub value: bool,
+}
+
+impl ToString for BooleanLiteral {
+    fn to_string(&self) -> String {
+        self.value.to_string()
+    }
+}
+
+impl Expression for BooleanLiteral { }
+
+pub struct NullLiteral;
+
+impl ToString for NullLiteral {
+    fn to_string(&self) -> String {
+        "null".to_string()
+    }
+}
+
+impl Expression for NullLiteral { }
+
+pub struct BigIntLiteral {
+    /// As written, without separators and the `n` suffix, e.g. `0x1f`.
+    pub digits: String,
+}
+
+impl ToString for BigIntLiteral {
+    fn to_string(&self) -> String {
+        format!("{}n", self.digits)
+    }
+}
+
+impl Expression for BigIntLiteral { }
+
+pub struct StringLiteral {
+    pub value: String,
+}
+
+impl ToString for StringLiteral {
+    fn to_string(&self) -> String {
+        quote(&self.value)
+    }
+}
+
+impl Expression for StringLiteral { }
+
+pub struct RegExpLiteral {
+    pub pattern: String,
+    pub flags: String,
+}
+
+impl ToString for RegExpLiteral {
+    fn to_string(&self) -> String {
+        format!("/{}/{}", self.pattern, self.flags)
+    }
+}
+
+impl Expression for RegExpLiteral { }
+
+pub struct TemplateElement {
+    /// `None` if the element holds an escape sequence that is only legal in tagged templates.
+    /// Printing uses `raw`; this is for evaluating the template.
+    #[allow(dead_code)]
+    pub cooked: Option<String>,
+    pub raw: String,
+}
+
+/// `quasis` always has one more element than `expressions`.
+pub struct TemplateLiteral {
+    pub quasis: Vec<TemplateElement>,
+    pub expressions: Vec<Box<dyn Expression>>,
+}
+
+impl ToString for TemplateLiteral {
+    fn to_string(&self) -> String {
+        let mut ret = String::from("`");
+        for (index, quasi) in self.quasis.iter().enumerate() {
+            ret += &quasi.raw;
+            if let Some(e) = self.expressions.get(index) {
+                ret += &format!("${{{}}}", e.to_string());
+            }
+        }
+        ret.push('`');
+        ret
+    }
+}
+
+impl Expression for TemplateLiteral { }
+
+pub struct TaggedTemplateExpression {
+    pub tag: Box<dyn Expression>,
+    pub quasi: TemplateLiteral,
+}
+
+impl ToString for TaggedTemplateExpression {
+    fn to_string(&self) -> String {
+        format!("{}{}", self.tag.to_string(), self.quasi.to_string())
+    }
+}
+
+impl Expression for TaggedTemplateExpression { }
+
+/// Print `s` as a double-quoted JavaScript string literal.
+fn quote(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret += "\\\"",
+            '\\' => ret += "\\\\",
+            '\n' => ret += "\\n",
+            '\r' => ret += "\\r",
+            '\t' => ret += "\\t",
+            '\u{2028}' | '\u{2029}' => ret += &format!("\\u{:04X}", c as u32),
+            c if c.is_control() => ret += &format!("\\x{:02X}", c as u32),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+pub struct Parameter {
+    pub id: Identifier,
+    pub default: Option<Box<dyn Expression>>,
+    /// `...args`, which is always the last parameter.
+    pub rest: bool,
+}
+
+impl ToString for Parameter {
+    fn to_string(&self) -> String {
+        let mut ret = if self.rest { format!("...{}", self.id.to_string()) } else { self.id.to_string() };
+        if let Some(default) = &self.default {
+            ret += &format!(" = {}", default.to_string());
+        }
+        ret
+    }
+}
+
+/// A function expression, or the function of a [FunctionDeclaration].
+pub struct FunctionLiteral {
+    pub name: Option<Identifier>,
+    pub params: Vec<Parameter>,
+    pub body: BlockStatement,
+    pub is_async: bool,
+}
+
+impl ToString for FunctionLiteral {
+    fn to_string(&self) -> String {
+        let name = self.name.as_ref().map_or(String::new(), |name| format!(" {}", name.to_string()));
+        let params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+        let prefix = if self.is_async { "async " } else { "" };
+        format!("{}function{}({}) {}", prefix, name, params.join(", "), self.body.to_string())
+    }
+}
+
+impl Expression for FunctionLiteral { }
+
+pub struct CallExpression {
+    pub callee: Box<dyn Expression>,
+    pub arguments: Vec<Box<dyn Expression>>,
+    /// `callee?.(arguments)`
+    pub optional: bool,
+}
+
+impl ToString for CallExpression {
+    fn to_string(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+        let op = if self.optional { "?." } else { "" };
+        format!("{}{}({})", self.callee.to_string(), op, arguments.join(", "))
+    }
+}
+
+impl Expression for CallExpression { }
+
+/// `object.property`, or `object[property]` if it is computed.
+pub struct MemberExpression {
+    pub object: Box<dyn Expression>,
+    pub property: Box<dyn Expression>,
+    pub computed: bool,
+    /// `object?.property` or `object?.[property]`
+    pub optional: bool,
+}
+
+impl ToString for MemberExpression {
+    fn to_string(&self) -> String {
+        let object = self.object.to_string();
+        let property = self.property.to_string();
+        match (self.computed, self.optional) {
+            (false, false) => format!("{}.{}", object, property),
+            (false, true) => format!("{}?.{}", object, property),
+            (true, false) => format!("{}[{}]", object, property),
+            (true, true) => format!("{}?.[{}]", object, property),
+        }
+    }
+}
+
+impl Expression for MemberExpression { }
+
+pub struct ArrayLiteral {
+    /// `None` for a hole, as in `[1, , 3]`.
+    pub elements: Vec<Option<Box<dyn Expression>>>,
+    /// The span of each element, which is empty for a hole. They locate errors if the
+    /// literal turns out to be a destructuring pattern.
+    pub spans: Vec<Span>,
+}
+
+impl ToString for ArrayLiteral {
+    fn to_string(&self) -> String {
+        let elements: Vec<String> = self.elements.iter()
+            .map(|e| e.as_ref().map_or(String::new(), |e| e.to_string()))
+            .collect();
+        let mut ret = format!("[{}", elements.join(", "));
+        // A hole at the end needs its comma.
+        if let Some(None) = self.elements.last() {
+            ret.push(',');
+        }
+        ret.push(']');
+        ret
+    }
+}
+
+impl Expression for ArrayLiteral { }
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PropertyKind {
+    Init,
+    Get,
+    Set,
+}
+
+pub struct Property {
+    /// An identifier, even for a reserved word, a string or number literal, or any
+    /// expression if `computed`.
+    pub key: Box<dyn Expression>,
+    pub computed: bool,
+    /// A [FunctionLiteral] for methods and accessors.
+    pub value: Box<dyn Expression>,
+    pub kind: PropertyKind,
+    pub method: bool,
+    /// `{a}`, short for `{a: a}`. In a pattern, `{a = 1}` is short for `{a: a = 1}`, so
+    /// the value is an [AssignmentExpression].
+    pub shorthand: bool,
+}
+
+impl ToString for Property {
+    fn to_string(&self) -> String {
+        let key = if self.computed { format!("[{}]", self.key.to_string()) } else { self.key.to_string() };
+        if self.shorthand {
+            return match self.value.downcast_ref::<AssignmentExpression>() {
+                Some(assignment) => format!("{} = {}", key, assignment.value.to_string()),
+                None => key,
+            };
+        }
+        let function = match self.value.downcast_ref::<FunctionLiteral>() {
+            Some(function) if self.method || self.kind != PropertyKind::Init => function,
+            _ => return format!("{}: {}", key, self.value.to_string()),
+        };
+        let prefix = match self.kind {
+            PropertyKind::Init if function.is_async => "async ",
+            PropertyKind::Init => "",
+            PropertyKind::Get => "get ",
+            PropertyKind::Set => "set ",
+        };
+        let params: Vec<String> = function.params.iter().map(|p| p.to_string()).collect();
+        format!("{}{}({}) {}", prefix, key, params.join(", "), function.body.to_string())
+    }
+}
+
+pub enum ObjectProperty {
+    Property(Property),
+    Spread(SpreadElement),
+}
+
+pub struct ObjectLiteral {
+    pub properties: Vec<ObjectProperty>,
+    /// The span of each property, as for [ArrayLiteral::spans].
+    pub spans: Vec<Span>,
+}
+
+impl ToString for ObjectLiteral {
+    fn to_string(&self) -> String {
+        let properties: Vec<String> = self.properties.iter().map(|p| match p {
+            ObjectProperty::Property(p) => p.to_string(),
+            ObjectProperty::Spread(spread) => spread.to_string(),
+        }).collect();
+        format!("{{{}}}", properties.join(", "))
+    }
+}
+
+impl Expression for ObjectLiteral { }
+
+/// `...argument` in an argument list, or an array or object literal.
+pub struct SpreadElement {
+    pub argument: Box<dyn Expression>,
+}
+
+impl ToString for SpreadElement {
+    fn to_string(&self) -> String {
+        format!("...{}", self.argument.to_string())
+    }
+}
+
+impl Expression for SpreadElement { }
+
+/// The body of an arrow function: an expression, or statements in braces.
+pub enum ArrowBody {
+    Expression(Box<dyn Expression>),
+    Block(BlockStatement),
+}
+
+pub struct ArrowFunction {
+    pub params: Vec<Parameter>,
+    pub body: ArrowBody,
+    pub is_async: bool,
+}
+
+impl ToString for ArrowFunction {
+    fn to_string(&self) -> String {
+        let params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+        let body = match &self.body {
+            ArrowBody::Expression(e) => e.to_string(),
+            ArrowBody::Block(block) => block.to_string(),
+        };
+        let prefix = if self.is_async { "async " } else { "" };
+        format!("{}({}) => {}", prefix, params.join(", "), body)
+    }
+}
+
+impl Expression for ArrowFunction { }
+
+/// Expressions separated by the comma operator.
+pub struct SequenceExpression {
+    pub expressions: Vec<Box<dyn Expression>>,
+}
+
+impl ToString for SequenceExpression {
+    fn to_string(&self) -> String {
+        let expressions: Vec<String> = self.expressions.iter().map(|e| e.to_string()).collect();
+        expressions.join(", ")
+    }
+}
+
+impl Expression for SequenceExpression { }
 
-impl ToString for NoOpExpression {
+/// An expression in parentheses. It is kept in the tree because `(a) = 1` is a valid
+/// assignment but `({a}) = 1` is not, and to print the source back as written.
+pub struct GroupedExpression {
+    pub expression: Box<dyn Expression>,
+}
+
+impl ToString for GroupedExpression {
     fn to_string(&self) -> String {
-        return "\"no-op\"".to_string()
+        format!("({})", self.expression.to_string())
     }
 }
 
-impl Expression for NoOpExpression {}
+impl Expression for GroupedExpression { }
 
 pub struct PrefixExpression {
     pub op: TokenKind,
@@ -76,11 +470,9 @@ pub struct PrefixExpression {
 
 impl ToString for PrefixExpression {
     fn to_string(&self) -> String {
-        match self.op {
-            TokenKind::Band => format!("(!{})", self.expression.to_string()),
-            TokenKind::Minus => format!("(-{})", self.expression.to_string()),
-            _ => panic!("invalid prefix operator")
-        }
+        // `typeof a` needs a space, `-a` doesn't.
+        let space = if self.op.is_keyword() { " " } else { "" };
+        format!("({}{}{})", operator_text(self.op), space, self.expression.to_string())
     }
 }
 
@@ -94,50 +486,143 @@ pub struct InfixExpression {
 
 impl ToString for InfixExpression {
     fn to_string(&self) -> String {
-        let op = match self.op {
-            TokenKind::Plus => "+",
-            TokenKind::Minus => "-",
-            TokenKind::Asterisk => "*",
-            TokenKind::Slash => "/",
-            TokenKind::LT => "<",
-            TokenKind::GT => ">",
-            TokenKind::EQ => "==",
-            TokenKind::NE => "!=",
-            TokenKind::SEQ => "===",
-            TokenKind::SNE => "!==",
-            _ => panic!("invalid infix operator {:?}", self.op)
-        };
+        let op = operator_text(self.op);
         format!("({} {} {})", self.lhs.to_string(), op, self.rhs.to_string())
     }
 }
 
 impl Expression for InfixExpression { }
 
-pub struct LetStatement {
-    pub id: Identifier,
+/// `&&`, `||` or `??`, which only evaluate their right-hand side when needed.
+pub struct LogicalExpression {
+    pub op: TokenKind,
+    pub lhs: Box<dyn Expression>,
+    pub rhs: Box<dyn Expression>,
+}
+
+impl ToString for LogicalExpression {
+    fn to_string(&self) -> String {
+        let op = operator_text(self.op);
+        format!("({} {} {})", self.lhs.to_string(), op, self.rhs.to_string())
+    }
+}
+
+impl Expression for LogicalExpression { }
+
+pub struct ConditionalExpression {
+    pub test: Box<dyn Expression>,
+    pub consequent: Box<dyn Expression>,
+    pub alternate: Box<dyn Expression>,
+}
+
+impl ToString for ConditionalExpression {
+    fn to_string(&self) -> String {
+        format!("({} ? {} : {})", self.test.to_string(), self.consequent.to_string(), self.alternate.to_string())
+    }
+}
+
+impl Expression for ConditionalExpression { }
+
+/// `=` or a compound assignment such as `+=`.
+pub struct AssignmentExpression {
+    pub op: TokenKind,
+    pub target: Box<dyn Expression>,
     pub value: Box<dyn Expression>,
 }
 
-impl ToString for LetStatement {
+impl ToString for AssignmentExpression {
+    fn to_string(&self) -> String {
+        let op = operator_text(self.op);
+        format!("({} {} {})", self.target.to_string(), op, self.value.to_string())
+    }
+}
+
+impl Expression for AssignmentExpression { }
+
+/// `++` or `--`, before or after its argument.
+pub struct UpdateExpression {
+    pub op: TokenKind,
+    pub prefix: bool,
+    pub argument: Box<dyn Expression>,
+}
+
+impl ToString for UpdateExpression {
+    fn to_string(&self) -> String {
+        let op = operator_text(self.op);
+        if self.prefix {
+            format!("({}{})", op, self.argument.to_string())
+        } else {
+            format!("({}{})", self.argument.to_string(), op)
+        }
+    }
+}
+
+impl Expression for UpdateExpression { }
+
+pub struct VariableDeclarator {
+    pub id: Identifier,
+    pub init: Option<Box<dyn Expression>>,
+}
+
+impl ToString for VariableDeclarator {
+    fn to_string(&self) -> String {
+        match &self.init {
+            Some(init) => format!("{} = {}", self.id.to_string(), init.to_string()),
+            None => self.id.to_string(),
+        }
+    }
+}
+
+/// `let`, `const` or `var` with one or more declarators.
+pub struct VariableDeclaration {
+    pub kind: TokenKind,
+    pub declarations: Vec<VariableDeclarator>,
+}
+
+impl VariableDeclaration {
+    /// The declaration without its `;`, as in the head of a `for` loop.
+    fn to_string_without_semicolon(&self) -> String {
+        let kind = operator_text(self.kind);
+        let declarations: Vec<String> = self.declarations.iter().map(|d| d.to_string()).collect();
+        format!("{} {}", kind, declarations.join(", "))
+    }
+}
+
+impl ToString for VariableDeclaration {
     fn to_string(&self) -> String {
-        format!("let {} = {};", self.id.to_string(), self.value.to_string())
+        format!("{};", self.to_string_without_semicolon())
     }
 }
 
-impl Statement for LetStatement {}
+impl Statement for VariableDeclaration {}
 
 pub struct ReturnStatement {
-    pub value: Box<dyn Expression>,
+    pub value: Option<Box<dyn Expression>>,
 }
 
 impl ToString for ReturnStatement {
     fn to_string(&self) -> String {
-        format!("return {};", self.value.to_string())
+        match &self.value {
+            Some(value) => format!("return {};", value.to_string()),
+            None => "return;".to_string(),
+        }
     }
 }
 
 impl Statement for ReturnStatement { }
 
+pub struct ThrowStatement {
+    pub argument: Box<dyn Expression>,
+}
+
+impl ToString for ThrowStatement {
+    fn to_string(&self) -> String {
+        format!("throw {};", self.argument.to_string())
+    }
+}
+
+impl Statement for ThrowStatement { }
+
 pub struct ExpressionStatement {
     pub expression: Box<dyn Expression>,
 }
@@ -150,4 +635,192 @@ impl ToString for ExpressionStatement {
 
 impl Statement for ExpressionStatement { }
 
+pub struct BlockStatement {
+    pub statements: Vec<Box<dyn Statement>>,
+    /// The names bound by `let` and `const` declarations directly in the block, which
+    /// are scoped to it.
+    pub lexical_names: Vec<String>,
+}
+
+impl ToString for BlockStatement {
+    fn to_string(&self) -> String {
+        if self.statements.is_empty() {
+            return "{}".to_string();
+        }
+        let statements: Vec<String> = self.statements.iter().map(|s| s.to_string()).collect();
+        format!("{{ {} }}", statements.join(" "))
+    }
+}
+
+impl Statement for BlockStatement { }
+
+pub struct IfStatement {
+    pub test: Box<dyn Expression>,
+    pub consequent: Box<dyn Statement>,
+    pub alternate: Option<Box<dyn Statement>>,
+}
+
+impl ToString for IfStatement {
+    fn to_string(&self) -> String {
+        let mut ret = format!("if ({}) {}", self.test.to_string(), self.consequent.to_string());
+        if let Some(alternate) = &self.alternate {
+            ret += " else ";
+            ret += &alternate.to_string();
+        }
+        ret
+    }
+}
+
+impl Statement for IfStatement { }
+
+pub struct FunctionDeclaration {
+    pub function: FunctionLiteral,
+}
+
+impl ToString for FunctionDeclaration {
+    fn to_string(&self) -> String {
+        self.function.to_string()
+    }
+}
+
+impl Statement for FunctionDeclaration { }
+
+pub struct WhileStatement {
+    pub test: Box<dyn Expression>,
+    pub body: Box<dyn Statement>,
+}
+
+impl ToString for WhileStatement {
+    fn to_string(&self) -> String {
+        format!("while ({}) {}", self.test.to_string(), self.body.to_string())
+    }
+}
+
+impl Statement for WhileStatement { }
+
+pub struct DoWhileStatement {
+    pub body: Box<dyn Statement>,
+    pub test: Box<dyn Expression>,
+}
+
+impl ToString for DoWhileStatement {
+    fn to_string(&self) -> String {
+        format!("do {} while ({});", self.body.to_string(), self.test.to_string())
+    }
+}
+
+impl Statement for DoWhileStatement { }
+
+/// What comes before the first `;` of a `for` loop, or before `in` or `of`.
+pub enum ForInit {
+    Declaration(VariableDeclaration),
+    Expression(Box<dyn Expression>),
+}
+
+impl ToString for ForInit {
+    fn to_string(&self) -> String {
+        match self {
+            ForInit::Declaration(declaration) => declaration.to_string_without_semicolon(),
+            ForInit::Expression(e) => e.to_string(),
+        }
+    }
+}
+
+pub struct ForStatement {
+    pub init: Option<ForInit>,
+    pub test: Option<Box<dyn Expression>>,
+    pub update: Option<Box<dyn Expression>>,
+    pub body: Box<dyn Statement>,
+}
+
+impl ToString for ForStatement {
+    fn to_string(&self) -> String {
+        let init = self.init.as_ref().map_or(String::new(), |init| init.to_string());
+        let test = self.test.as_ref().map_or(String::new(), |test| format!(" {}", test.to_string()));
+        let update = self.update.as_ref().map_or(String::new(), |update| format!(" {}", update.to_string()));
+        format!("for ({};{};{}) {}", init, test, update, self.body.to_string())
+    }
+}
+
+impl Statement for ForStatement { }
+
+pub struct ForInStatement {
+    pub left: ForInit,
+    pub right: Box<dyn Expression>,
+    pub body: Box<dyn Statement>,
+}
+
+impl ToString for ForInStatement {
+    fn to_string(&self) -> String {
+        format!("for ({} in {}) {}", self.left.to_string(), self.right.to_string(), self.body.to_string())
+    }
+}
+
+impl Statement for ForInStatement { }
+
+pub struct ForOfStatement {
+    pub left: ForInit,
+    pub right: Box<dyn Expression>,
+    pub body: Box<dyn Statement>,
+}
+
+impl ToString for ForOfStatement {
+    fn to_string(&self) -> String {
+        format!("for ({} of {}) {}", self.left.to_string(), self.right.to_string(), self.body.to_string())
+    }
+}
+
+impl Statement for ForOfStatement { }
+
+pub struct BreakStatement {
+    pub label: Option<Identifier>,
+}
+
+impl ToString for BreakStatement {
+    fn to_string(&self) -> String {
+        match &self.label {
+            Some(label) => format!("break {};", label.to_string()),
+            None => "break;".to_string(),
+        }
+    }
+}
+
+impl Statement for BreakStatement { }
+
+pub struct ContinueStatement {
+    pub label: Option<Identifier>,
+}
+
+impl ToString for ContinueStatement {
+    fn to_string(&self) -> String {
+        match &self.label {
+            Some(label) => format!("continue {};", label.to_string()),
+            None => "continue;".to_string(),
+        }
+    }
+}
+
+impl Statement for ContinueStatement { }
+
+pub struct LabeledStatement {
+    pub label: Identifier,
+    pub body: Box<dyn Statement>,
+}
+
+impl ToString for LabeledStatement {
+    fn to_string(&self) -> String {
+        format!("{}: {}", self.label.to_string(), self.body.to_string())
+    }
+}
+
+impl Statement for LabeledStatement { }
+
+pub struct EmptyStatement;
+
+impl ToString for EmptyStatement {
+    fn to_string(&self) -> String {
+        ";".to_string()
+    }
+}
 
+impl Statement for EmptyStatement { }
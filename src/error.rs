@@ -0,0 +1,26 @@
+use std::fmt;
+
+use crate::lexer::span::{LineIndex, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    pub fn new<S: Into<String>>(message: S, span: Span) -> Self {
+        SyntaxError{ message: message.into(), span }
+    }
+
+    /// Formats the error as `line:column: message`.
+    pub fn describe(&self, index: &LineIndex) -> String {
+        format!("{}: {}", index.position(self.span.start), self.message)
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
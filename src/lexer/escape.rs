@@ -0,0 +1,150 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EscapeError {
+    InvalidHex,         // \x not followed by two hex digits
+    InvalidUnicode,     // \u not followed by four hex digits or {hex digits}
+    CodePointTooLarge,  // \u{110000} and above
+    OctalInTemplate,    // \1 or \08 inside a template
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            EscapeError::InvalidHex => "invalid hexadecimal escape sequence",
+            EscapeError::InvalidUnicode => "invalid Unicode escape sequence",
+            EscapeError::CodePointTooLarge => "undefined Unicode code-point",
+            EscapeError::OctalInTemplate => "octal escape sequences are not allowed in template strings",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// Reads the escape sequence that follows a backslash at the start of `s`.
+///
+/// Returns the code point it stands for, which may be a lone surrogate, or `None`
+/// for a line continuation, together with the number of bytes it spans.
+pub fn read_escape(s: &str, template: bool) -> Result<(Option<u32>, usize), EscapeError> {
+    let bytes = s.as_bytes();
+    let c = match s.chars().next() {
+        Some(c) => c,
+        None => return Ok((None, 0)),
+    };
+    let value = match c {
+        'n' => 0x0A,
+        't' => 0x09,
+        'r' => 0x0D,
+        'b' => 0x08,
+        'f' => 0x0C,
+        'v' => 0x0B,
+        '\r' => {
+            let len = if bytes.get(1) == Some(&b'\n') { 2 } else { 1 };
+            return Ok((None, len));
+        }
+        '\n' | '\u{2028}' | '\u{2029}' => return Ok((None, c.len_utf8())),
+        'x' => {
+            let value = bytes.get(1..3)
+                .and_then(hex_value)
+                .ok_or(EscapeError::InvalidHex)?;
+            return Ok((Some(value), 3));
+        }
+        'u' => return read_unicode(bytes).map(|(value, len)| (Some(value), len)),
+        '0' if !bytes.get(1).is_some_and(u8::is_ascii_digit) => 0,
+        '0'..='9' if template => return Err(EscapeError::OctalInTemplate),
+        '0'..='7' => {
+            // Legacy octal escape, at most \377.
+            let max = if c <= '3' { 3 } else { 2 };
+            let len = bytes.iter()
+                .take(max)
+                .take_while(|b| (b'0'..=b'7').contains(*b))
+                .count();
+            let value = u32::from_str_radix(&s[..len], 8).unwrap();
+            return Ok((Some(value), len));
+        }
+        c => c as u32,
+    };
+    Ok((Some(value), c.len_utf8()))
+}
+
+/// Reads `uHHHH` or `u{H...}`.
+pub fn read_unicode(bytes: &[u8]) -> Result<(u32, usize), EscapeError> {
+    if bytes.first() != Some(&b'u') {
+        return Err(EscapeError::InvalidUnicode);
+    }
+    if bytes.get(1) != Some(&b'{') {
+        let value = bytes.get(1..5)
+            .and_then(hex_value)
+            .ok_or(EscapeError::InvalidUnicode)?;
+        return Ok((value, 5));
+    }
+    let digits = bytes[2..].iter().take_while(|b| b.is_ascii_hexdigit()).count();
+    if digits == 0 || bytes.get(2 + digits) != Some(&b'}') {
+        return Err(EscapeError::InvalidUnicode);
+    }
+    let mut value: u32 = 0;
+    for b in &bytes[2..2 + digits] {
+        value = value * 16 + (*b as char).to_digit(16).unwrap();
+        if value > 0x10FFFF {
+            return Err(EscapeError::CodePointTooLarge);
+        }
+    }
+    Ok((value, digits + 3))
+}
+
+fn hex_value(digits: &[u8]) -> Option<u32> {
+    let mut value = 0;
+    for b in digits {
+        value = value * 16 + (*b as char).to_digit(16)?;
+    }
+    Some(value)
+}
+
+/// Decodes the body of a string or template literal, without its delimiters.
+///
+/// Surrogate escapes are paired up as in UTF-16; a lone surrogate decodes to U+FFFD.
+pub fn cook(body: &str, template: bool) -> Result<String, EscapeError> {
+    let mut units: Vec<u16> = Vec::with_capacity(body.len());
+    let mut buf = [0; 2];
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '\\' => {
+                let (value, len) = read_escape(rest, template)?;
+                rest = &rest[len..];
+                match value.map(char::from_u32) {
+                    Some(Some(c)) => units.extend_from_slice(c.encode_utf16(&mut buf)),
+                    Some(None) => units.push(value.unwrap() as u16),
+                    None => {}
+                }
+            }
+            '\r' => {
+                // Only templates can hold a raw CR; it reads as LF, and so does CRLF.
+                rest = rest.strip_prefix('\n').unwrap_or(rest);
+                units.push(0x0A);
+            }
+            c => units.extend_from_slice(c.encode_utf16(&mut buf)),
+        }
+    }
+    Ok(String::from_utf16_lossy(&units))
+}
+
+/// Decodes the `\u` escapes in an identifier the lexer has accepted. Invalid ones
+/// have been reported by the lexer and are dropped.
+pub fn cook_identifier(raw: &str) -> String {
+    let mut ret = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(index) = rest.find('\\') {
+        ret += &rest[..index];
+        rest = &rest[index + 1..];
+        match read_unicode(rest.as_bytes()) {
+            Ok((value, len)) => {
+                ret.extend(char::from_u32(value));
+                rest = &rest[len..];
+            }
+            Err(_) => rest = rest.strip_prefix('u').unwrap_or(rest),
+        }
+    }
+    ret += rest;
+    ret
+}
@@ -1,16 +1,29 @@
 #![cfg(test)]
 
+use crate::error::SyntaxError;
+use crate::lexer::span::{LineIndex, Position, Span};
 use crate::lexer::token::*;
-use super::Lexer;
+use super::source::{Encoding, Source};
+use super::stream::StreamLexer;
+use super::{escape, trivia, Lexer};
 
 fn test_lexer(input: &str, expects: &[Token]) {
     let mut lexer = Lexer::new(input);
     for expect in expects {
         let token = lexer.next_token();
-        assert_eq!(&token, expect);
+        assert_token(&token, expect);
     }
 }
 
+fn messages(errors: &[SyntaxError]) -> Vec<&str> {
+    errors.iter().map(|e| e.message.as_str()).collect()
+}
+
+/// Compares what a token is, ignoring the trivia around it.
+fn assert_token(token: &Token, expect: &Token) {
+    assert_eq!((token.kind, token.literal), (expect.kind, expect.literal));
+}
+
 #[test]
 fn test_simple_token() {
     let input = "=+-*/!<>(){},;";
@@ -33,7 +46,7 @@ fn test_simple_token() {
         TokenKind::Semicolon,
 
         TokenKind::EOF,
-    ].map(| kind | Token::from(kind) );
+    ].map(Token::from);
     test_lexer(input, &expects);
 }
 
@@ -101,11 +114,72 @@ fn test_float() {
 fn test_illegal_number2() {
     let input = "1.2.9";
     let expects = [
-        Token::new_number("1.2.9"),
+        Token::new_number("1.2"),
+        Token::new_number(".9"),
+        TokenKind::EOF.into(),
     ];
     test_lexer(input, &expects);
 }
 
+#[test]
+fn test_numeric_grammar() {
+    let input = "0b1010 0O17 0xDead_Beef .5 5. 1e3 1E-7 2.5e+3 1_000_000 0.000_1 089 08.5 0777 \
+        12n 0x1fn 0n 1.e2 5..x";
+    let expects = [
+        Token::new_number("0b1010"),
+        Token::new_number("0O17"),
+        Token::new_number("0xDead_Beef"),
+        Token::new_number(".5"),
+        Token::new_number("5."),
+        Token::new_number("1e3"),
+        Token::new_number("1E-7"),
+        Token::new_number("2.5e+3"),
+        Token::new_number("1_000_000"),
+        Token::new_number("0.000_1"),
+        Token::new_number("089"),
+        Token::new_number("08.5"),
+        Token::new_number("0777"),
+        Token::new(TokenKind::BigInt, "12n"),
+        Token::new(TokenKind::BigInt, "0x1fn"),
+        Token::new(TokenKind::BigInt, "0n"),
+        Token::new_number("1.e2"),
+        Token::new_number("5."),
+        TokenKind::Dot.into(),
+        Token::new_id("x"),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_malformed_numbers() {
+    let cases = [
+        ("12abc", "identifier starts immediately after numeric literal"),
+        ("3in", "identifier starts immediately after numeric literal"),
+        ("0x", "expected digits after 0x"),
+        ("0b102", "invalid digit in binary literal"),
+        ("0o78", "invalid digit in octal literal"),
+        ("1__0", "numeric separators are only allowed between digits"),
+        ("1_", "numeric separators are only allowed between digits"),
+        ("0x_1", "numeric separators are only allowed between digits"),
+        ("1._5", "numeric separators are only allowed between digits"),
+        ("0_1", "numeric separators are not allowed after a leading 0"),
+        ("1e", "expected digits in exponent"),
+        ("1e+_1", "numeric separators are only allowed between digits"),
+        ("1.5n", "invalid BigInt literal"),
+        ("1e3n", "invalid BigInt literal"),
+        ("017n", "invalid BigInt literal"),
+    ];
+    for (input, message) in cases {
+        let mut lexer = Lexer::new(input);
+        let token = lexer.next_token();
+        assert_eq!(token.literal, input);
+        assert_token(&lexer.next_token(), &TokenKind::EOF.into());
+        assert_eq!(messages(lexer.errors()), [message], "lexing {}", input);
+        assert_eq!(lexer.errors()[0].span, Span::new(0, input.len()));
+    }
+}
+
 #[test]
 fn test_assign() {
     let input = r"
@@ -182,6 +256,46 @@ function foo() {
     test_lexer(input, &expects);
 }
 
+#[test]
+fn test_reserved_words() {
+    let input = "var const typeof instanceof in null this enum debugger \
+        let static yield await async of get set as from implements";
+    let expects = [
+        TokenKind::Var.into(),
+        TokenKind::Const.into(),
+        TokenKind::Typeof.into(),
+        TokenKind::Instanceof.into(),
+        TokenKind::In.into(),
+        TokenKind::Null.into(),
+        TokenKind::This.into(),
+        TokenKind::Enum.into(),
+        TokenKind::Debugger.into(),
+
+        TokenKind::Let.into(),
+        TokenKind::Static.into(),
+        TokenKind::Yield.into(),
+        TokenKind::Await.into(),
+        TokenKind::Async.into(),
+        TokenKind::Of.into(),
+        TokenKind::Get.into(),
+        TokenKind::Set.into(),
+        TokenKind::As.into(),
+        TokenKind::From.into(),
+        // Only reserved in strict mode, which the parser knows about.
+        Token::new_id("implements"),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+
+    for (text, kind) in KEYWORKS.iter() {
+        assert!(kind.is_keyword());
+        assert_eq!(Token::from(*kind).literal, *text);
+    }
+    assert!(!TokenKind::Identifier.is_keyword());
+    assert!(TokenKind::Of.is_contextual_keyword());
+    assert!(!TokenKind::In.is_contextual_keyword());
+}
+
 #[test]
 fn test_function_call() {
     let input = r"
@@ -242,4 +356,709 @@ fn test_eq_ne() {
         TokenKind::Semicolon.into(),
     ];
     test_lexer(input, &expects);
-}
\ No newline at end of file
+}
+#[test]
+fn test_string() {
+    let input = r#"
+"hello" 'x' "it's" 'say "hi"' "a\"b" ''
+"\n\t\\" "\x41B\u{43}" "line \
+continued"
+    "#;
+    let expects = [
+        Token::new(TokenKind::String, r#""hello""#),
+        Token::new(TokenKind::String, "'x'"),
+        Token::new(TokenKind::String, r#""it's""#),
+        Token::new(TokenKind::String, r#"'say "hi"'"#),
+        Token::new(TokenKind::String, r#""a\"b""#),
+        Token::new(TokenKind::String, "''"),
+        Token::new(TokenKind::String, r#""\n\t\\""#),
+        Token::new(TokenKind::String, r#""\x41B\u{43}""#),
+        Token::new(TokenKind::String, "\"line \\\ncontinued\""),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_unterminated_string() {
+    let input = "\"abc\nfoo 'bar";
+    let mut lexer = Lexer::new(input);
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::String, "\"abc"));
+    assert_token(&lexer.next_token(), &Token::new_id("foo"));
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::String, "'bar"));
+    assert_token(&lexer.next_token(), &TokenKind::EOF.into());
+    assert_eq!(messages(lexer.errors()), [
+        "unterminated string literal",
+        "unterminated string literal",
+    ]);
+}
+
+#[test]
+fn test_bad_escapes() {
+    let input = r#" "\x4" "\u12" "\u{}" "\u{110000}" "ok" "#;
+    let mut lexer = Lexer::new(input);
+    for _ in 0..5 {
+        assert_eq!(lexer.next_token().kind, TokenKind::String);
+    }
+    assert_eq!(lexer.next_token().kind, TokenKind::EOF);
+    assert_eq!(messages(lexer.errors()), [
+        "invalid hexadecimal escape sequence",
+        "invalid Unicode escape sequence",
+        "invalid Unicode escape sequence",
+        "undefined Unicode code-point",
+    ]);
+    // An out-of-range code point is reported over the whole escape.
+    assert_eq!(lexer.errors()[3].span, Span::new(22, 32));
+    assert_eq!(&input[22..32], r"\u{110000}");
+}
+
+#[test]
+fn test_cook() {
+    let cases = [
+        (r"plain", "plain"),
+        (r"\n\t\r\b\f\v\0", "\n\t\r\u{8}\u{c}\u{b}\0"),
+        (r#"\'\"\\"#, "'\"\\"),
+        (r"\x41b\u{1F600}", "Ab\u{1F600}"),
+        (r"😀", "\u{1F600}"),
+        (r"\uD83D!", "\u{FFFD}!"),
+        ("a\\\nb\\\r\nc\\\u{2028}d", "abcd"),
+        (r"\101\7\08\8\q", "A\u{7}\u{0}88q"),
+        ("é\\é", "éé"),
+    ];
+    for (body, expect) in cases {
+        assert_eq!(escape::cook(body, false).unwrap(), expect, "cooking {}", body);
+    }
+    assert_eq!(escape::cook(r"\1", true), Err(escape::EscapeError::OctalInTemplate));
+    assert_eq!(escape::cook(r"\xZ1", false), Err(escape::EscapeError::InvalidHex));
+}
+
+#[test]
+fn test_template() {
+    let input = r"`plain` `a${b}c${ { `e${f}` } }g` `\`${'}'}`";
+    let expects = [
+        Token::new(TokenKind::Template, "`plain`"),
+        Token::new(TokenKind::TemplateHead, "`a${"),
+        Token::new_id("b"),
+        Token::new(TokenKind::TemplateMiddle, "}c${"),
+        TokenKind::LBrace.into(),
+        Token::new(TokenKind::TemplateHead, "`e${"),
+        Token::new_id("f"),
+        Token::new(TokenKind::TemplateTail, "}`"),
+        TokenKind::RBrace.into(),
+        Token::new(TokenKind::TemplateTail, "}g`"),
+        Token::new(TokenKind::TemplateHead, r"`\`${"),
+        Token::new(TokenKind::String, "'}'"),
+        Token::new(TokenKind::TemplateTail, "}`"),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_unterminated_template() {
+    let input = "`abc${x}def";
+    let mut lexer = Lexer::new(input);
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::TemplateHead, "`abc${"));
+    assert_token(&lexer.next_token(), &Token::new_id("x"));
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::TemplateTail, "}def"));
+    assert_token(&lexer.next_token(), &TokenKind::EOF.into());
+    assert_eq!(messages(lexer.errors()), ["unterminated template literal"]);
+}
+
+#[test]
+fn test_comments() {
+    let input = "// header\nlet x = 1; // one\n/* block */ x /* inline */ + /*\n*/ 2 // end";
+    let expects = [
+        TokenKind::Let.into(),
+        Token::new_id("x"),
+        TokenKind::Assign.into(),
+        Token::new_number("1"),
+        TokenKind::Semicolon.into(),
+        Token::new_id("x"),
+        TokenKind::Plus.into(),
+        Token::new_number("2"),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_trivia() {
+    let input = "// header\nlet x; // one\n/* block */ x /* inline */ + /*\n*/ 2 // end\n";
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        tokens.push(token);
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+    let trivia: Vec<_> = tokens.iter()
+        .map(|t| (t.leading_trivia, t.trailing_trivia))
+        .collect();
+    assert_eq!(trivia, [
+        ("// header\n", " "),       // let
+        ("", ""),                   // x
+        ("", " // one"),            // ;
+        ("\n/* block */ ", " /* inline */ "), // x
+        ("", " "),                  // +
+        ("/*\n*/ ", " // end"),     // 2
+        ("\n", ""),                 // EOF
+    ]);
+
+    let comments: Vec<_> = tokens[3].leading_comments().map(|c| c.text).collect();
+    assert_eq!(comments, ["/* block */"]);
+    let comments: Vec<_> = tokens[5].trailing_comments().map(|c| c.text).collect();
+    assert_eq!(comments, ["// end"]);
+    assert!(lexer.errors().is_empty());
+}
+
+#[test]
+fn test_trivia_pieces() {
+    let pieces: Vec<_> = trivia::pieces(" // a\n\t/* b */\n/* c")
+        .map(|p| (p.kind, p.text))
+        .collect();
+    assert_eq!(pieces, [
+        (trivia::TriviaKind::Whitespace, " "),
+        (trivia::TriviaKind::LineComment, "// a"),
+        (trivia::TriviaKind::Whitespace, "\n\t"),
+        (trivia::TriviaKind::BlockComment, "/* b */"),
+        (trivia::TriviaKind::Whitespace, "\n"),
+        (trivia::TriviaKind::BlockComment, "/* c"),
+    ]);
+}
+
+#[test]
+fn test_unterminated_comment() {
+    let input = "a /* never closed";
+    let mut lexer = Lexer::new(input);
+    let token = lexer.next_token();
+    assert_token(&token, &Token::new_id("a"));
+    assert_eq!(token.trailing_trivia, " /* never closed");
+    assert_token(&lexer.next_token(), &TokenKind::EOF.into());
+    assert_eq!(messages(lexer.errors()), ["unterminated comment"]);
+}
+
+#[test]
+fn test_spans() {
+    let input = "let s = 'é'; // x\n  `a${b}`";
+    let mut lexer = Lexer::new(input);
+    let mut spans = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if !token.literal.is_empty() {
+            assert_eq!(&input[token.span.start .. token.span.end], token.literal);
+        }
+        spans.push(token.span);
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+    assert_eq!(spans, [
+        Span::new(0, 3),
+        Span::new(4, 5),
+        Span::new(6, 7),
+        Span::new(8, 12),
+        Span::new(12, 13),
+        Span::new(21, 25),
+        Span::new(25, 26),
+        Span::new(26, 28),
+        Span::new(28, 28),
+    ]);
+}
+
+#[test]
+fn test_error_spans() {
+    let input = "'abc\n\"\\xZ\" /* open";
+    let mut lexer = Lexer::new(input);
+    while lexer.next_token().kind != TokenKind::EOF {}
+    let spans: Vec<_> = lexer.errors().iter().map(|e| e.span).collect();
+    assert_eq!(spans, [Span::new(0, 4), Span::new(6, 8), Span::new(11, 18)]);
+}
+
+#[test]
+fn test_line_index() {
+    let input = "ab\ncd\r\nef\rgh\n\n😀x";
+    let index = LineIndex::new(input);
+    let position = |line, column, utf16_column| Position{ line, column, utf16_column };
+    assert_eq!(index.position(0), position(1, 1, 1));
+    assert_eq!(index.position(2), position(1, 3, 3));
+    assert_eq!(index.position(3), position(2, 1, 1));
+    assert_eq!(index.position(5), position(2, 3, 3));
+    assert_eq!(index.position(7), position(3, 1, 1));
+    assert_eq!(index.position(10), position(4, 1, 1));
+    assert_eq!(index.position(13), position(5, 1, 1));
+    assert_eq!(index.position(14), position(6, 1, 1));
+    assert_eq!(index.position(18), position(6, 2, 3));
+    // Clamped to the input and to char boundaries.
+    assert_eq!(index.position(16), position(6, 1, 1));
+    assert_eq!(index.position(100), position(6, 3, 4));
+    assert_eq!(index.position(18).to_string(), "6:2");
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    let input = "café $el _x Ωmega 变量 a\u{200d}b \\u0061bc \\u{62}\\u0063 x\\u0031 \u{309b}\u{309c} a\\u309B";
+    let expects = [
+        Token::new_id("café"),
+        Token::new_id("$el"),
+        Token::new_id("_x"),
+        Token::new_id("Ωmega"),
+        Token::new_id("变量"),
+        Token::new_id("a\u{200d}b"),
+        Token::new_id("\\u0061bc"),
+        Token::new_id("\\u{62}\\u0063"),
+        Token::new_id("x\\u0031"),
+        // In ID_Start and ID_Continue, but not in XID_Start or XID_Continue.
+        Token::new_id("\u{309b}\u{309c}"),
+        Token::new_id("a\\u309B"),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+
+    let mut lexer = Lexer::new("\\u309B\\u{FE7E}");
+    let token = lexer.next_token();
+    assert_eq!((token.kind, token.literal), (TokenKind::Identifier, "\\u309B\\u{FE7E}"));
+    assert!(lexer.errors().is_empty());
+}
+
+#[test]
+fn test_bad_identifier_escapes() {
+    let input = "\\u0031x a\\u002db \\u{69}f \\x";
+    let mut lexer = Lexer::new(input);
+    while lexer.next_token().kind != TokenKind::EOF {}
+    assert_eq!(messages(lexer.errors()), [
+        "invalid Unicode escape sequence in identifier",
+        "invalid Unicode escape sequence in identifier",
+        "keywords cannot contain escaped characters",
+        "invalid Unicode escape sequence in identifier",
+    ]);
+}
+
+#[test]
+fn test_unicode_whitespace() {
+    let input = "a\u{a0}b\u{feff}c\u{2003}d\u{b}e\u{2028}f\u{2029}g\u{3000}h";
+    let mut lexer = Lexer::new(input);
+    let mut names = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+        assert_eq!(token.kind, TokenKind::Identifier);
+        names.push(token.literal);
+    }
+    assert_eq!(names, ["a", "b", "c", "d", "e", "f", "g", "h"]);
+    assert!(lexer.errors().is_empty());
+}
+
+#[test]
+fn test_unicode_line_terminators() {
+    let input = "a // comment\u{2028}b /* x */\u{2029}c";
+    let mut lexer = Lexer::new(input);
+    let a = lexer.next_token();
+    assert_eq!(a.trailing_trivia, " // comment");
+    let b = lexer.next_token();
+    assert_eq!(b.leading_trivia, "\u{2028}");
+    assert_eq!(b.trailing_trivia, " /* x */");
+    assert_eq!(lexer.next_token().leading_trivia, "\u{2029}");
+
+    let index = LineIndex::new(input);
+    assert_eq!(index.position(b.span.start).line, 2);
+}
+
+#[test]
+fn test_non_ascii_illegal() {
+    let input = "a § b";
+    let mut lexer = Lexer::new(input);
+    assert_token(&lexer.next_token(), &Token::new_id("a"));
+    let illegal = lexer.next_token();
+    assert_eq!(illegal.kind, TokenKind::Illegal);
+    assert_eq!(illegal.span, Span::new(2, 4));
+    assert_eq!(illegal.literal, "§");
+    assert_token(&lexer.next_token(), &Token::new_id("b"));
+}
+
+#[test]
+fn test_regexp() {
+    let input = r"/ab+c/gi /[/\]]+/ /\// /a/dgimsuy";
+    let mut lexer = Lexer::new(input);
+    let expects = [
+        Token::new(TokenKind::RegExp, "/ab+c/gi"),
+        Token::new(TokenKind::RegExp, r"/[/\]]+/"),
+        Token::new(TokenKind::RegExp, r"/\//"),
+        Token::new(TokenKind::RegExp, "/a/dgimsuy"),
+    ];
+    for expect in expects {
+        assert_token(&lexer.next_regexp_token(), &expect);
+    }
+    assert_token(&lexer.next_regexp_token(), &TokenKind::EOF.into());
+    assert!(lexer.errors().is_empty());
+}
+
+#[test]
+fn test_regexp_errors() {
+    let cases = [
+        ("/abc", "unterminated regular expression literal"),
+        ("/a\\\n/", "unterminated regular expression literal"),
+        ("/[/\n]/", "unterminated regular expression literal"),
+        ("/a/gg", "invalid regular expression flags"),
+        ("/a/x", "invalid regular expression flags"),
+        ("/a/uv", "invalid regular expression flags"),
+    ];
+    for (input, message) in cases {
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.next_regexp_token().kind, TokenKind::RegExp);
+        assert_eq!(messages(lexer.errors()), [message], "lexing {:?}", input);
+    }
+}
+
+#[test]
+fn test_rescan() {
+    let input = "`${ /}/ }`";
+    let mut lexer = Lexer::new(input);
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::TemplateHead, "`${"));
+    let checkpoint = lexer.checkpoint();
+    assert_token(&lexer.next_token(), &TokenKind::Slash.into());
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::TemplateTail, "}/ }`"));
+
+    lexer.reset(checkpoint);
+    let regexp = lexer.next_regexp_token();
+    assert_token(&regexp, &Token::new(TokenKind::RegExp, "/}/"));
+    assert_eq!(regexp.span, Span::new(4, 7));
+    assert_eq!(regexp.trailing_trivia, " ");
+    assert_token(&lexer.next_token(), &Token::new(TokenKind::TemplateTail, "}`"));
+    assert_token(&lexer.next_token(), &TokenKind::EOF.into());
+}
+
+#[test]
+fn test_punctuators() {
+    let input = "<= >= && || ?? ?. ** ++ -- => % & | ^ ~ << >> >>> ? : [ ] ... \
+        += -= *= /= %= **= <<= >>= >>>= &= |= ^= &&= ||= ??=";
+    let expects = [
+        TokenKind::LE,
+        TokenKind::GE,
+        TokenKind::And,
+        TokenKind::Or,
+        TokenKind::Nullish,
+        TokenKind::QuestionDot,
+        TokenKind::Exponent,
+        TokenKind::Increment,
+        TokenKind::Decrement,
+        TokenKind::Arrow,
+        TokenKind::Percent,
+        TokenKind::BitAnd,
+        TokenKind::BitOr,
+        TokenKind::BitXor,
+        TokenKind::BitNot,
+        TokenKind::Shl,
+        TokenKind::Shr,
+        TokenKind::UShr,
+        TokenKind::Question,
+        TokenKind::Colon,
+        TokenKind::LBracket,
+        TokenKind::RBracket,
+        TokenKind::Ellipsis,
+
+        TokenKind::PlusAssign,
+        TokenKind::MinusAssign,
+        TokenKind::AsteriskAssign,
+        TokenKind::SlashAssign,
+        TokenKind::PercentAssign,
+        TokenKind::ExponentAssign,
+        TokenKind::ShlAssign,
+        TokenKind::ShrAssign,
+        TokenKind::UShrAssign,
+        TokenKind::BitAndAssign,
+        TokenKind::BitOrAssign,
+        TokenKind::BitXorAssign,
+        TokenKind::AndAssign,
+        TokenKind::OrAssign,
+        TokenKind::NullishAssign,
+
+        TokenKind::EOF,
+    ].map(Token::from);
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_longest_match() {
+    let input = "a+++b a?.5:c x>>>=y ....5 !==== =>=";
+    let expects = [
+        Token::new_id("a"),
+        TokenKind::Increment.into(),
+        TokenKind::Plus.into(),
+        Token::new_id("b"),
+
+        Token::new_id("a"),
+        TokenKind::Question.into(),
+        Token::new_number(".5"),
+        TokenKind::Colon.into(),
+        Token::new_id("c"),
+
+        Token::new_id("x"),
+        TokenKind::UShrAssign.into(),
+        Token::new_id("y"),
+
+        TokenKind::Ellipsis.into(),
+        Token::new_number(".5"),
+
+        TokenKind::SNE.into(),
+        TokenKind::EQ.into(),
+
+        TokenKind::Arrow.into(),
+        TokenKind::Assign.into(),
+        TokenKind::EOF.into(),
+    ];
+    test_lexer(input, &expects);
+}
+
+#[test]
+fn test_newline_before() {
+    let input = "a b\nc /* x */ d /*\n*/ e // f\n\r\ng\u{2028}h";
+    let mut lexer = Lexer::new(input);
+    let mut flags = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+        flags.push((token.literal, token.newline_before));
+    }
+    assert_eq!(flags, [
+        ("a", false),
+        ("b", false),
+        ("c", true),
+        ("d", false),
+        ("e", true),
+        ("g", true),
+        ("h", true),
+    ]);
+}
+
+#[test]
+fn test_iterator() {
+    let kinds: Vec<_> = Lexer::new("a + 1 // end").map(|t| t.kind).collect();
+    assert_eq!(kinds, [TokenKind::Identifier, TokenKind::Plus, TokenKind::Number]);
+
+    let tokens: Vec<_> = Lexer::lossless("a + 1 // end").collect();
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[3].kind, TokenKind::EOF);
+    assert_eq!(tokens[2].trailing_trivia, " // end");
+
+    let tokens: Vec<_> = Lexer::lossless("a\n/* end */\n").collect();
+    assert_eq!(tokens[1].kind, TokenKind::EOF);
+    assert_eq!(tokens[1].leading_trivia, "\n/* end */\n");
+}
+
+#[test]
+fn test_token_text() {
+    let input = "{ } >>>= ?. ... => ( ; typeof";
+    let tokens: Vec<_> = Lexer::new(input).collect();
+    let texts: Vec<_> = tokens.iter().map(|t| t.literal).collect();
+    assert_eq!(texts, ["{", "}", ">>>=", "?.", "...", "=>", "(", ";", "typeof"]);
+    for token in tokens {
+        assert_eq!(token.kind.text(), Some(token.literal));
+    }
+    assert_eq!(TokenKind::Identifier.text(), None);
+}
+
+/// Checks that the tokens of `input` and their trivia add up to it exactly.
+fn assert_round_trip(input: &str) {
+    let mut text = String::new();
+    for token in Lexer::lossless(input) {
+        assert_eq!(token.span.start, text.len() + token.leading_trivia.len(), "{:?}", input);
+        text += token.leading_trivia;
+        text += token.literal;
+        text += token.trailing_trivia;
+    }
+    assert_eq!(text, input);
+}
+
+#[test]
+fn test_lossless_round_trip() {
+    let inputs = [
+        "",
+        "  \n",
+        "let a = 1; // x\n/* y\n */ b",
+        "`a${ {b: `c${d}`} }e` + '\\u{1F600}'",
+        "0x 1__2 09.5 1n.x 'abc\ndef \"\\",
+        "§ \0 @#",
+        "/* unterminated",
+        "`unterminated ${ a",
+        "#!/usr/bin/env node\nx",
+        "\u{feff}a\r\nb\u{2028}c",
+    ];
+    for input in inputs {
+        assert_round_trip(input);
+    }
+
+    // Glue random fragments together, to hit combinations that are easy to get wrong.
+    let fragments = [
+        "a", "1", "0x", "1_", ".5", "e", "n", "'", "\"", "`", "${", "}", "{", "/*", "*/",
+        "//", "/", "\n", "\r", " ", "\t", "\u{2028}", "é", "\\", "\\u0061", "?.",
+        "?", ">>>=", "=>", "§", "\0", "[", "]", "=", "let",
+    ];
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    for _ in 0..2000 {
+        let mut input = String::new();
+        for _ in 0..12 {
+            // xorshift
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            input += fragments[(state % fragments.len() as u64) as usize];
+        }
+        assert_round_trip(&input);
+    }
+}
+
+/// Hands out its input a byte at a time, so that every token and UTF-8 sequence
+/// straddles reads.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> std::io::Read for ByteReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((b, rest)) if !buf.is_empty() => {
+                buf[0] = *b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_stream_lexer() {
+    let input = "let a = `x${ {b: `y${c}`} }z` ?.5:0; // été\n\
+        \u{2028}café /* multi\nline */ >>>= 0x1_f /* one */ \"\\u0065\" 1__2 '😀'";
+    let mut lexer = Lexer::new(input);
+    let mut expects = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        expects.push(OwnedToken::from(token));
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+
+    let mut stream = StreamLexer::new(ByteReader(input.as_bytes()));
+    for expect in &expects {
+        assert_eq!(&stream.next_token().unwrap(), expect);
+    }
+    assert_eq!(stream.next_token().unwrap().kind, TokenKind::EOF);
+    assert_eq!(stream.errors(), lexer.errors());
+
+    let tokens: Vec<_> = StreamLexer::new(input.as_bytes()).map(Result::unwrap).collect();
+    assert_eq!(tokens, expects[..expects.len() - 1]);
+}
+
+#[test]
+fn test_stream_lexer_long_input() {
+    // Longer than one read chunk, with tokens and chars that straddle the short reads.
+    let mut input = String::new();
+    for i in 0..2000 {
+        input.push_str(&format!("let é{i} = `a${{ {{b: '😀'}} }}c` + 0x{i:x} /* {i} */;\n"));
+    }
+    assert!(input.len() > 8 * 1024);
+    let mut lexer = Lexer::new(&input);
+    let mut expects = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        expects.push(OwnedToken::from(token));
+        if token.kind == TokenKind::EOF {
+            break;
+        }
+    }
+
+    let mut stream = StreamLexer::new(ByteReader(input.as_bytes()));
+    for expect in &expects {
+        assert_eq!(&stream.next_token().unwrap(), expect);
+    }
+    assert!(stream.errors().is_empty());
+}
+
+#[test]
+fn test_stream_lexer_invalid_utf8() {
+    let mut stream = StreamLexer::new(&b"a \xff b"[..]);
+    let e = stream.next_token().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+
+    // Cut off in the middle of a char.
+    let mut stream = StreamLexer::new(&"a \u{e9}".as_bytes()[..3]);
+    assert_eq!(stream.next_token().unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_hashbang() {
+    let input = "#!/usr/bin/env lessjs\nlet x";
+    let mut lexer = Lexer::new(input);
+    let token = lexer.next_token();
+    assert_token(&token, &TokenKind::Let.into());
+    assert_eq!(token.span, Span::new(22, 25));
+    assert!(token.newline_before);
+    let pieces: Vec<_> = trivia::pieces(token.leading_trivia).map(|p| (p.kind, p.text)).collect();
+    assert_eq!(pieces, [
+        (trivia::TriviaKind::Hashbang, "#!/usr/bin/env lessjs"),
+        (trivia::TriviaKind::Whitespace, "\n"),
+    ]);
+
+    let token = StreamLexer::new(input.as_bytes()).next_token().unwrap();
+    assert_eq!(token.span, Span::new(22, 25));
+
+    // Only the very start of the input can be a hashbang.
+    let expects = [
+        Token::new(TokenKind::Illegal, "#"),
+        TokenKind::Band.into(),
+        Token::new_id("x"),
+    ];
+    test_lexer(" #!x", &expects);
+}
+
+#[test]
+fn test_source_encodings() {
+    let source = Source::new(b"\xEF\xBB\xBF#!/usr/bin/env lessjs\r\na").unwrap();
+    assert_eq!(source.encoding(), Encoding::Utf8);
+    assert!(source.has_bom());
+    assert_eq!(source.hashbang(), Some("#!/usr/bin/env lessjs"));
+    let token = source.lexer().next_token();
+    assert_eq!(token.span, Span::new(23, 24));
+    assert_eq!(source.file_span(token.span), Span::new(26, 27));
+
+    let text = "é = '😀' + b";
+    for (bom, encoding) in [([0xFF, 0xFE], Encoding::Utf16LE), ([0xFE, 0xFF], Encoding::Utf16BE)] {
+        let mut bytes = bom.to_vec();
+        for unit in text.encode_utf16() {
+            let pair = if encoding == Encoding::Utf16LE { unit.to_le_bytes() } else { unit.to_be_bytes() };
+            bytes.extend_from_slice(&pair);
+        }
+        let source = Source::new(&bytes).unwrap();
+        assert_eq!(source.encoding(), encoding);
+        assert_eq!(source.text(), text);
+        assert_eq!(source.hashbang(), None);
+        let spans: Vec<_> = source.lexer().map(|t| source.file_span(t.span)).collect();
+        // Two bytes for the BOM, then two per UTF-16 code unit.
+        assert_eq!(spans, [
+            Span::new(2, 4),
+            Span::new(6, 8),
+            Span::new(10, 18),
+            Span::new(20, 22),
+            Span::new(24, 26),
+        ]);
+    }
+
+    let source = Source::new(b"a").unwrap();
+    assert_eq!((source.encoding(), source.has_bom()), (Encoding::Utf8, false));
+}
+
+#[test]
+fn test_source_decoding_errors() {
+    let message = |bytes: &[u8]| Source::new(bytes).unwrap_err().to_string();
+    assert_eq!(message(b"ab\xFF"), "invalid UTF-8 at byte 2");
+    assert_eq!(message(b"\xEF\xBB\xBFab\xFF"), "invalid UTF-8 at byte 5");
+    assert_eq!(message(b"\xFF\xFEa\x00b"), "truncated UTF-16 at byte 4");
+    assert_eq!(message(b"\xFF\xFEa\x00\x00\xD8b\x00"), "unpaired UTF-16 surrogate at byte 4");
+}
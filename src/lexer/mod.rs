@@ -1,143 +1,585 @@
 
+pub mod escape;
 mod lexer_tests;
+pub mod source;
+pub mod span;
+pub mod stream;
 pub mod token;
+pub mod trivia;
 
+use crate::error::SyntaxError;
+use span::Span;
 use token::*;
 
+/// What an open `{` on the lexer's stack was opened by. A `}` closing a template
+/// substitution resumes scanning the template instead of producing `RBrace`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Brace,          // {
+    Substitution,   // ${
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    position: usize,
+    modes: Vec<Mode>,
+    errors: usize,
+}
+
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
     read_position: usize,
-    ch: u8,
+    ch: char,
+    modes: Vec<Mode>,
+    // Whether the input can start with a hashbang, i.e. it is the start of a file.
+    hashbang: bool,
+    // Whether iterating yields the final `EOF` token too.
+    lossless: bool,
+    done: bool,
+
+    errors: Vec<SyntaxError>,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Lexer {
+    pub fn new(input: &'a str) -> Lexer<'a> {
         let mut lexer = Lexer{
-            input: input,
+            input,
             position: 0,
             read_position: 0,
-            ch: b'0',
+            ch: '\0',
+            modes: Vec::new(),
+            hashbang: true,
+            lossless: false,
+            done: false,
+            errors: Vec::new(),
         };
         lexer.read_char();
         return lexer;
     }
 
+    /// A lexer whose iterator also yields the `EOF` token, which holds the trivia at
+    /// the end of the input. Concatenating the leading trivia, literal and trailing
+    /// trivia of every token it yields reproduces the input exactly.
+    #[allow(dead_code)]
+    pub fn lossless(input: &'a str) -> Lexer<'a> {
+        let mut lexer = Lexer::new(input);
+        lexer.lossless = true;
+        return lexer;
+    }
+
+    pub fn errors(&self) -> &Vec<SyntaxError> { &self.errors }
+
+    pub fn take_errors(&mut self) -> Vec<SyntaxError> {
+        std::mem::take(&mut self.errors)
+    }
+
     pub fn next_token(&mut self) -> Token<'a> {
-        self.skip_whitespace();
-        let token = match self.ch {
-            b'=' => {
-                if self.peak_char() == b'=' {
-                    if self.peak_third_char() == b'=' {
-                        self.read_char();
-                        self.read_char();
-                        Token::from(TokenKind::SEQ)
-                    } else {
-                        self.read_char();
-                        Token::from(TokenKind::EQ)
-                    }
-                } else {
-                    Token::from(TokenKind::Assign)
+        self.next_token_with_goal(false)
+    }
+
+    /// Like [Lexer::next_token], but a `/` here starts a regular expression literal
+    /// rather than being a division. Only the parser knows which one the source means,
+    /// so it asks for this after seeing `/` where an expression should start,
+    /// resetting the lexer to a checkpoint taken before that token first.
+    pub fn next_regexp_token(&mut self) -> Token<'a> {
+        self.next_token_with_goal(true)
+    }
+
+    /// Saves the lexer state, to go back to with [Lexer::reset].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint{ position: self.position, modes: self.modes.clone(), errors: self.errors.len() }
+    }
+
+    /// Goes back to `checkpoint`, forgetting the errors found since.
+    pub fn reset(&mut self, checkpoint: Checkpoint) {
+        self.read_position = checkpoint.position;
+        self.read_char();
+        self.modes = checkpoint.modes;
+        self.errors.truncate(checkpoint.errors);
+    }
+
+    fn next_token_with_goal(&mut self, regexp: bool) -> Token<'a> {
+        let leading_trivia = self.read_trivia(false);
+        let start = self.position;
+        let mut token = if regexp && self.ch == '/' {
+            self.read_regexp()
+        } else {
+            self.read_token()
+        };
+        token.span = Span::new(start, self.position);
+        debug_assert_eq!(token.literal, &self.input[start .. self.position]);
+        token.newline_before = leading_trivia.contains(is_line_terminator);
+        token.leading_trivia = leading_trivia;
+        token.trailing_trivia = self.read_trivia(true);
+        return token;
+    }
+
+    fn read_token(&mut self) -> Token<'a> {
+        let start = self.position;
+        let kind = match self.ch {
+            '{' => {
+                self.modes.push(Mode::Brace);
+                TokenKind::LBrace
+            }
+            '}' => {
+                if self.modes.pop() == Some(Mode::Substitution) {
+                    return self.read_template();
                 }
+                TokenKind::RBrace
             }
-            b'!' => {
-                if self.peak_char() == b'=' {
-                    if self.peak_third_char() == b'=' {
-                        self.read_char();
-                        self.read_char();
-                        Token::from(TokenKind::SNE)
-                    } else {
-                        self.read_char();
-                        Token::from(TokenKind::NE)
-                    }
-                } else {
-                    Token::from(TokenKind::Band)
-                }
-            }
-            b'+' => Token::from(TokenKind::Plus),
-            b'-' => Token::from(TokenKind::Minus),
-            b'*' => Token::from(TokenKind::Asterisk),
-            b'/' => Token::from(TokenKind::Slash),
-            b'<' => Token::from(TokenKind::LT),
-            b'>' => Token::from(TokenKind::GT),
-            b',' => Token::from(TokenKind::Commas),
-            b';' => Token::from(TokenKind::Semicolon),
-            b'(' => Token::from(TokenKind::LParen),
-            b')' => Token::from(TokenKind::RParen),
-            b'{' => Token::from(TokenKind::LBrace),
-            b'}' => Token::from(TokenKind::RBrace),
-            b'.' => Token::from(TokenKind::Dot),
-            0 => Token::from(TokenKind::EOF),
+            '.' if self.peak_char().is_ascii_digit() => return self.read_number(),
+            '"' | '\'' => return self.read_string(),
+            '`' => return self.read_template(),
+            '\0' if self.is_eof() => return Token::new(TokenKind::EOF, ""),
             _ => {
-                if self.ch.is_ascii_alphabetic() || self.ch == b'_' {
-                    let name = self.read_identifier();
-                    return Lexer::lookup_ident(name);
+                if is_id_start(self.ch) || self.ch == '\\' {
+                    return self.read_identifier();
                 }
                 if self.ch.is_ascii_digit() {
-                    let value = self.read_number();
-                    return Token::new(TokenKind::Number, value)
+                    return self.read_number();
+                }
+                if let Some(kind) = self.read_punctuator() {
+                    return Token::new(kind, &self.input[start .. self.position]);
                 }
-                Token::new(TokenKind::Illegal, "")
+                TokenKind::Illegal
             }
         };
         self.read_char();
-        return token;
+        return Token::new(kind, &self.input[start .. self.position]);
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.ch.is_ascii_whitespace() {
+    fn read_punctuator(&mut self) -> Option<TokenKind> {
+        let rest = &self.input[self.position..];
+        let (text, mut kind) = *PUNCTUATORS.iter().find(|(text, _)| rest.starts_with(text))?;
+        // `a?.5:b` is a conditional, not optional chaining.
+        if kind == TokenKind::QuestionDot && rest[2..].starts_with(|c: char| c.is_ascii_digit()) {
+            kind = TokenKind::Question;
+        }
+        let len = if kind == TokenKind::Question { 1 } else { text.len() };
+        let end = self.position + len;
+        while self.position < end {
             self.read_char();
         }
+        Some(kind)
     }
 
-    fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0;
-        } else {
-            self.ch = self.input.as_bytes()[self.read_position];
+    /// Reads whitespace and comments. Trailing trivia stops before a line terminator,
+    /// and before a block comment that spans lines, so whatever separates two lines
+    /// goes to the leading trivia of the token on the next one.
+    fn read_trivia(&mut self, trailing: bool) -> &'a str {
+        let start = self.position;
+        loop {
+            match self.ch {
+                // `#!/usr/bin/env lessjs` reads like a line comment, but only right at the start.
+                '#' if self.position == 0 && self.hashbang && self.peak_char() == '!' => {
+                    while !is_line_terminator(self.ch) && !self.is_eof() {
+                        self.read_char();
+                    }
+                }
+                c if is_line_terminator(c) => {
+                    if trailing {
+                        break;
+                    }
+                    self.read_char();
+                }
+                '/' if self.peak_char() == '/' => {
+                    while !is_line_terminator(self.ch) && !self.is_eof() {
+                        self.read_char();
+                    }
+                }
+                '/' if self.peak_char() == '*' => {
+                    if trailing && self.block_comment_spans_lines() {
+                        break;
+                    }
+                    self.skip_block_comment();
+                }
+                c if is_whitespace(c) => self.read_char(),
+                _ => break,
+            }
+        }
+        &self.input[start .. self.position]
+    }
+
+    fn block_comment_spans_lines(&self) -> bool {
+        let body = &self.input[self.position + 2 ..];
+        let end = body.find("*/").unwrap_or(body.len());
+        body[..end].contains(is_line_terminator)
+    }
+
+    fn skip_block_comment(&mut self) {
+        let start = self.position;
+        self.read_char();
+        self.read_char();
+        loop {
+            if self.ch == '*' && self.peak_char() == '/' {
+                self.read_char();
+                self.read_char();
+                return;
+            }
+            if self.is_eof() {
+                self.error("unterminated comment", start);
+                return;
+            }
+            self.read_char();
         }
+    }
+
+    fn read_char(&mut self) {
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.position..].chars().next() {
+            Some(c) => {
+                self.ch = c;
+                self.read_position += c.len_utf8();
+            }
+            None => self.ch = '\0',
+        }
     }
 
-    fn peak_char(&self) -> u8 {
-        if self.read_position >= self.input.len() {
-            0
-        } else {
-            self.input.as_bytes()[self.read_position]
+    fn peak_char(&self) -> char {
+        self.input[self.read_position..].chars().next().unwrap_or('\0')
+    }
+
+    fn is_eof(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn read_identifier(&mut self) -> Token<'a> {
+        let start = self.position;
+        let mut escaped = false;
+        loop {
+            if self.ch == '\\' {
+                escaped = true;
+                self.read_identifier_escape(start);
+            } else if (self.position == start && is_id_start(self.ch))
+                    || (self.position > start && is_id_continue(self.ch)) {
+                self.read_char();
+            } else {
+                break;
+            }
+        }
+        let name = &self.input[start .. self.position];
+        if escaped {
+            // Contextual keywords spelled with escapes are plain identifiers.
+            if KEYWORKS.get(escape::cook_identifier(name).as_str())
+                    .is_some_and(|kind| !kind.is_contextual_keyword()) {
+                self.error("keywords cannot contain escaped characters", start);
+            }
+            return Token::new_id(name);
         }
+        return Lexer::lookup_ident(name);
     }
 
-    fn peak_third_char(&self) -> u8 {
-        let pos = self.read_position + 1;
-        if pos >= self.input.len() {
-            0
-        } else {
-            self.input.as_bytes()[pos]
+    /// Reads a `\u` escape inside an identifier, which has to stand for a character
+    /// that could have been written there directly.
+    fn read_identifier_escape(&mut self, start: usize) {
+        let escape_start = self.position;
+        self.read_char();
+        let valid = match escape::read_unicode(&self.input.as_bytes()[self.position..]) {
+            Ok((value, len)) => {
+                for _ in 0..len {
+                    self.read_char();
+                }
+                char::from_u32(value).is_some_and(|c| {
+                    if escape_start == start { is_id_start(c) } else { is_id_continue(c) }
+                })
+            }
+            Err(_) => false,
+        };
+        if !valid {
+            self.error("invalid Unicode escape sequence in identifier", escape_start);
         }
     }
 
-    fn read_identifier(&mut self) -> &'a str {
+    fn read_regexp(&mut self) -> Token<'a> {
         let start = self.position;
-        while self.ch.is_ascii_alphanumeric() || self.ch == b'_' {
+        let mut in_class = false;
+        self.read_char();
+        loop {
+            match self.ch {
+                '/' if !in_class => break,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '\\' if !is_line_terminator(self.peak_char()) => self.read_char(),
+                _ => {}
+            }
+            if is_line_terminator(self.ch) || self.is_eof() {
+                self.error("unterminated regular expression literal", start);
+                return Token::new(TokenKind::RegExp, &self.input[start .. self.position]);
+            }
             self.read_char();
         }
-        &self.input[start .. self.position]
+        self.read_char();
+
+        let flags_start = self.position;
+        while is_id_continue(self.ch) {
+            self.read_char();
+        }
+        let flags = &self.input[flags_start .. self.position];
+        let valid = flags.chars().enumerate().all(|(index, c)| {
+            "dgimsuvy".contains(c) && !flags[..index].contains(c)
+        });
+        if !valid || (flags.contains('u') && flags.contains('v')) {
+            self.error("invalid regular expression flags", flags_start);
+        }
+        return Token::new(TokenKind::RegExp, &self.input[start .. self.position]);
     }
 
-    fn read_number(&mut self) -> &'a str {
+    /// Reads a numeric literal, reporting at most one error for it. After an error
+    /// the rest of the literal is still consumed, so that it makes one token.
+    fn read_number(&mut self) -> Token<'a> {
         let start = self.position;
-        while self.ch.is_ascii_alphanumeric() || self.ch == b'.' {
+        let errors = self.errors.len();
+        let mut bigint_allowed = true;
+        let radix = match (self.ch, self.peak_char()) {
+            ('0', 'x' | 'X') => 16,
+            ('0', 'o' | 'O') => 8,
+            ('0', 'b' | 'B') => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.read_char();
+            self.read_char();
+            if !self.read_digits(radix, start) {
+                let msg = format!("expected digits after {}", &self.input[start .. self.position]);
+                self.error(&msg, start);
+            }
+            if radix != 16 && self.ch.is_ascii_digit() {
+                let name = if radix == 2 { "binary" } else { "octal" };
+                self.error(&format!("invalid digit in {} literal", name), start);
+            }
+        } else if self.ch == '0' && (self.peak_char().is_ascii_digit() || self.peak_char() == '_') {
+            // A legacy octal literal like 0777, or a decimal one with a leading zero like 089.
+            bigint_allowed = false;
+            while self.ch.is_ascii_digit() {
+                self.read_char();
+            }
+            if self.ch == '_' {
+                self.error("numeric separators are not allowed after a leading 0", start);
+            }
+            let legacy_octal = self.input[start .. self.position].bytes().all(|b| b < b'8');
+            if !legacy_octal {
+                self.read_decimal_tail(start);
+            }
+        } else {
+            if self.ch != '.' {
+                self.read_digits(10, start);
+            }
+            bigint_allowed = !self.read_decimal_tail(start);
+        }
+
+        let mut kind = TokenKind::Number;
+        if self.ch == 'n' {
+            if bigint_allowed {
+                kind = TokenKind::BigInt;
+            } else {
+                self.error("invalid BigInt literal", start);
+            }
             self.read_char();
         }
-        &self.input[start .. self.position]
+        if is_id_start(self.ch) || self.ch.is_ascii_digit() || self.ch == '\\' {
+            self.error("identifier starts immediately after numeric literal", start);
+        }
+        if self.errors.len() > errors {
+            self.errors.truncate(errors + 1);
+            while is_id_continue(self.ch) || self.ch == '.' && self.peak_char().is_ascii_digit() {
+                self.read_char();
+            }
+            self.errors[errors].span.end = self.position;
+        }
+        return Token::new(kind, &self.input[start .. self.position]);
+    }
+
+    /// Reads digits in `radix` with `_` separators between them. Returns false if there
+    /// are no digits.
+    fn read_digits(&mut self, radix: u32, start: usize) -> bool {
+        let mut digits = false;
+        loop {
+            if self.ch.is_digit(radix) {
+                digits = true;
+            } else if self.ch == '_' {
+                if !digits || !self.peak_char().is_digit(radix) {
+                    self.error("numeric separators are only allowed between digits", start);
+                }
+            } else {
+                return digits;
+            }
+            self.read_char();
+        }
+    }
+
+    /// Reads the optional fraction and exponent of a decimal literal. Returns whether
+    /// there was any.
+    fn read_decimal_tail(&mut self, start: usize) -> bool {
+        let mut found = false;
+        if self.ch == '.' {
+            found = true;
+            self.read_char();
+            if self.ch == '_' {
+                self.error("numeric separators are only allowed between digits", start);
+            }
+            self.read_digits(10, start);
+        }
+        if matches!(self.ch, 'e' | 'E') {
+            found = true;
+            self.read_char();
+            if matches!(self.ch, '+' | '-') {
+                self.read_char();
+            }
+            if !self.read_digits(10, start) {
+                self.error("expected digits in exponent", start);
+            }
+        }
+        return found;
+    }
+
+    fn read_string(&mut self) -> Token<'a> {
+        let quote = self.ch;
+        let start = self.position;
+        self.read_char();
+        while self.ch != quote {
+            match self.ch {
+                '\\' => self.skip_escape(),
+                // U+2028 and U+2029 are allowed in strings.
+                '\n' | '\r' => {
+                    self.error("unterminated string literal", start);
+                    return Token::new(TokenKind::String, &self.input[start .. self.position]);
+                }
+                '\0' if self.is_eof() => {
+                    self.error("unterminated string literal", start);
+                    return Token::new(TokenKind::String, &self.input[start ..]);
+                }
+                _ => self.read_char(),
+            }
+        }
+        self.read_char();
+        Token::new(TokenKind::String, &self.input[start .. self.position])
+    }
+
+    /// Reads a template from its opening backtick, or its continuation from the `}`
+    /// closing a substitution, up to the closing backtick or the next `${`.
+    fn read_template(&mut self) -> Token<'a> {
+        let start = self.position;
+        let head = self.ch == '`';
+        self.read_char();
+        loop {
+            match self.ch {
+                '`' => {
+                    self.read_char();
+                    break;
+                }
+                '$' if self.peak_char() == '{' => {
+                    self.read_char();
+                    self.read_char();
+                    self.modes.push(Mode::Substitution);
+                    let kind = if head { TokenKind::TemplateHead } else { TokenKind::TemplateMiddle };
+                    return Token::new(kind, &self.input[start .. self.position]);
+                }
+                '\\' => {
+                    // Whether an escape is valid depends on the template being tagged,
+                    // which only the parser knows.
+                    self.read_char();
+                    self.read_char();
+                }
+                '\0' if self.is_eof() => {
+                    self.error("unterminated template literal", start);
+                    break;
+                }
+                _ => self.read_char(),
+            }
+        }
+        let kind = if head { TokenKind::Template } else { TokenKind::TemplateTail };
+        Token::new(kind, &self.input[start .. self.position])
+    }
+
+    fn skip_escape(&mut self) {
+        debug_assert_eq!(self.ch, '\\');
+        let rest = &self.input[self.read_position..];
+        match escape::read_escape(rest, false) {
+            Ok((_, len)) => {
+                // `len` counts bytes, but the escape may end in a multi-byte char.
+                let end = self.read_position + len;
+                while self.position < end {
+                    self.read_char();
+                }
+            }
+            Err(e) => {
+                let len = match e {
+                    // `\u{110000}` is well formed, just out of range, so the error covers
+                    // all of it.
+                    escape::EscapeError::CodePointTooLarge => rest.find('}').map_or(0, |end| end + 1),
+                    _ => rest.chars().next().map_or(0, char::len_utf8),
+                };
+                let span = Span::new(self.position, self.read_position + len);
+                self.errors.push(SyntaxError::new(e.to_string(), span));
+                if e == escape::EscapeError::CodePointTooLarge {
+                    while self.position < span.end - 1 {
+                        self.read_char();
+                    }
+                }
+                self.read_char();
+            }
+        }
+    }
+
+    /// Records an error spanning from `start` to the current position.
+    fn error(&mut self, message: &str, start: usize) {
+        let span = Span::new(start, self.position);
+        self.errors.push(SyntaxError::new(message, span));
     }
 
     fn lookup_ident(name: &'a str) -> Token<'a> {
         if let Some(kind) = KEYWORKS.get(name) {
-            (*kind).into()
+            Token::new(*kind, name)
         } else {
             Token::new(TokenKind::Identifier, name)
         }
     }
-}
\ No newline at end of file
+}
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    /// Yields the tokens up to `EOF`, which is only included in lossless mode.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.next_token();
+        if token.kind == TokenKind::EOF {
+            self.done = true;
+            if !self.lossless {
+                return None;
+            }
+        }
+        return Some(token);
+    }
+}
+
+/// WhiteSpace in ECMAScript: TAB, VT, FF, ZWNBSP and the Space_Separator category.
+pub fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\u{b}' | '\u{c}' | '\u{feff}'
+        | ' ' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}')
+}
+
+pub fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+/// ECMAScript identifiers use ID_Start and ID_Continue, which unicode-ident doesn't
+/// provide. The XID properties it has differ only in leaving out these characters,
+/// because of how they normalize under NFKC.
+fn is_nfkc_excluded_id_start(c: char) -> bool {
+    matches!(c, '\u{37a}' | '\u{e33}' | '\u{eb3}' | '\u{309b}' | '\u{309c}'
+        | '\u{fc5e}'..='\u{fc63}' | '\u{fdfa}' | '\u{fdfb}'
+        | '\u{fe70}' | '\u{fe72}' | '\u{fe74}' | '\u{fe76}' | '\u{fe78}' | '\u{fe7a}' | '\u{fe7c}' | '\u{fe7e}'
+        | '\u{ff9e}' | '\u{ff9f}')
+}
+
+pub fn is_id_start(c: char) -> bool {
+    c == '$' || c == '_' || unicode_ident::is_xid_start(c) || is_nfkc_excluded_id_start(c)
+}
+
+pub fn is_id_continue(c: char) -> bool {
+    c == '$' || c == '\u{200c}' || c == '\u{200d}' || unicode_ident::is_xid_continue(c)
+        || is_nfkc_excluded_id_start(c)
+}
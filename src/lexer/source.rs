@@ -0,0 +1,114 @@
+
+use std::io::{self, Read};
+
+use super::span::Span;
+use super::Lexer;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Encoding {
+    Utf8,
+    Utf16LE,
+    Utf16BE,
+}
+
+/// A script as loaded from a file, decoded to UTF-8 with its byte order mark removed.
+///
+/// Spans from a [Lexer] over the source are byte offsets into [Source::text]; use
+/// [Source::file_span] to get the offsets into the file they came from.
+#[derive(Debug)]
+pub struct Source {
+    text: String,
+    encoding: Encoding,
+    bom: bool,
+}
+
+// Nothing loads scripts from files yet; only the tests do.
+#[allow(dead_code)]
+impl Source {
+    /// Decodes `bytes`. A byte order mark picks the encoding, which is UTF-8 without one.
+    pub fn new(bytes: &[u8]) -> io::Result<Source> {
+        let (encoding, bom) = match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16LE, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16BE, 2),
+            _ => (Encoding::Utf8, 0),
+        };
+        let bytes = &bytes[bom..];
+        let text = match encoding {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(text) => text.to_string(),
+                Err(e) => return Err(invalid_data("invalid UTF-8", bom + e.valid_up_to())),
+            },
+            Encoding::Utf16LE | Encoding::Utf16BE => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(invalid_data("truncated UTF-16", bom + bytes.len() - 1));
+                }
+                let units = bytes.chunks_exact(2).map(|pair| match encoding {
+                    Encoding::Utf16LE => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                let mut text = String::with_capacity(bytes.len() / 2);
+                let mut offset = bom;
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => {
+                            text.push(c);
+                            offset += c.len_utf16() * 2;
+                        }
+                        Err(_) => return Err(invalid_data("unpaired UTF-16 surrogate", offset)),
+                    }
+                }
+                text
+            }
+        };
+        Ok(Source{ text, encoding, bom: bom > 0 })
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Source> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Source::new(&bytes)
+    }
+
+    /// The decoded text, starting after the byte order mark.
+    pub fn text(&self) -> &str { &self.text }
+
+    pub fn encoding(&self) -> Encoding { self.encoding }
+
+    pub fn has_bom(&self) -> bool { self.bom }
+
+    /// The `#!` line the script starts with, without its line terminator. The lexer
+    /// treats it as trivia.
+    pub fn hashbang(&self) -> Option<&str> {
+        if !self.text.starts_with("#!") {
+            return None;
+        }
+        let end = self.text.find(super::is_line_terminator).unwrap_or(self.text.len());
+        Some(&self.text[..end])
+    }
+
+    pub fn lexer(&self) -> Lexer<'_> {
+        Lexer::new(&self.text)
+    }
+
+    /// Maps an offset into [Source::text] to a byte offset into the file.
+    pub fn file_offset(&self, offset: usize) -> usize {
+        let bom = match (self.encoding, self.bom) {
+            (_, false) => 0,
+            (Encoding::Utf8, true) => 3,
+            (_, true) => 2,
+        };
+        match self.encoding {
+            Encoding::Utf8 => bom + offset,
+            Encoding::Utf16LE | Encoding::Utf16BE => bom + self.text[..offset].encode_utf16().count() * 2,
+        }
+    }
+
+    pub fn file_span(&self, span: Span) -> Span {
+        Span::new(self.file_offset(span.start), self.file_offset(span.end))
+    }
+}
+
+fn invalid_data(message: &str, offset: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} at byte {}", message, offset))
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// A half-open range of byte offsets into the source.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        debug_assert!(start <= end);
+        Span{ start, end }
+    }
+}
+
+/// A human-readable location. All fields are 1-based.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    /// Counted in chars.
+    pub column: usize,
+    /// Counted in UTF-16 code units, as editors and JavaScript itself do.
+    pub utf16_column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps byte offsets to lines and columns. `\n`, `\r\n`, a lone `\r`, U+2028 and
+/// U+2029 each end a line.
+pub struct LineIndex<'a> {
+    input: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        let mut chars = input.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\r' if chars.peek().map(|(_, c)| *c) == Some('\n') => {}
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => line_starts.push(index + c.len_utf8()),
+                _ => {}
+            }
+        }
+        LineIndex{ input, line_starts }
+    }
+
+    /// `offset` is clamped to the input, and moved back to a char boundary if it falls
+    /// inside a char.
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.input.len());
+        while !self.input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let text = &self.input[self.line_starts[line] .. offset];
+        Position{
+            line: line + 1,
+            column: text.chars().count() + 1,
+            utf16_column: text.encode_utf16().count() + 1,
+        }
+    }
+}
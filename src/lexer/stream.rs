@@ -0,0 +1,147 @@
+
+use std::io::{self, Read};
+
+use crate::error::SyntaxError;
+use super::span::Span;
+use super::token::{OwnedToken, TokenKind};
+use super::{Lexer, Mode};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// How many chars the lexer may look at past the end of a token and its trailing
+/// trivia to decide where they end, as for `?.5`.
+const LOOKAHEAD: usize = 3;
+
+/// A lexer that reads its source from an [io::Read] a chunk at a time, rather than
+/// needing all of it up front like [Lexer].
+///
+/// Each token is lexed by a [Lexer] over the buffered text. A token that runs up to
+/// the end of the buffer might continue in the next chunk, so it is lexed again once
+/// more has been read. Spans are byte offsets from the start of the stream.
+pub struct StreamLexer<R: Read> {
+    reader: R,
+    // Decoded text. It's compacted only before reading more, not after every token.
+    buffer: String,
+    // Offset in `buffer` of the first character not made into a token yet.
+    start: usize,
+    // Bytes read after the last complete UTF-8 sequence.
+    pending: Vec<u8>,
+    // Offset of the start of `buffer` in the stream.
+    offset: usize,
+    eof: bool,
+    modes: Vec<Mode>,
+
+    errors: Vec<SyntaxError>,
+}
+
+// Nothing reads scripts from a stream yet; only the tests do.
+#[allow(dead_code)]
+impl<R: Read> StreamLexer<R> {
+    pub fn new(reader: R) -> Self {
+        StreamLexer{
+            reader,
+            buffer: String::new(),
+            start: 0,
+            pending: Vec::new(),
+            offset: 0,
+            eof: false,
+            modes: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &Vec<SyntaxError> { &self.errors }
+
+    pub fn take_errors(&mut self) -> Vec<SyntaxError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Reads the next token. Once the input is exhausted this keeps returning `EOF`.
+    pub fn next_token(&mut self) -> io::Result<OwnedToken> {
+        self.next_token_with_goal(false)
+    }
+
+    /// Like [StreamLexer::next_token], but a `/` starts a regular expression literal.
+    /// See [Lexer::next_regexp_token].
+    pub fn next_regexp_token(&mut self) -> io::Result<OwnedToken> {
+        self.next_token_with_goal(true)
+    }
+
+    fn next_token_with_goal(&mut self, regexp: bool) -> io::Result<OwnedToken> {
+        loop {
+            let mut lexer = Lexer::new(&self.buffer[self.start..]);
+            lexer.modes = self.modes.clone();
+            lexer.hashbang = self.offset + self.start == 0;
+            let token = lexer.next_token_with_goal(regexp);
+            let rest = &self.buffer[self.start + lexer.position..];
+            if !self.eof && rest.chars().nth(LOOKAHEAD).is_none() {
+                self.fill_buffer()?;
+                continue;
+            }
+
+            let mut token = OwnedToken::from(token);
+            token.span = self.shift(token.span);
+            for mut e in lexer.take_errors() {
+                e.span = self.shift(e.span);
+                self.errors.push(e);
+            }
+            self.modes = lexer.modes;
+            self.start += lexer.position;
+            return Ok(token);
+        }
+    }
+
+    fn shift(&self, span: Span) -> Span {
+        Span::new(span.start + self.offset + self.start, span.end + self.offset + self.start)
+    }
+
+    /// Reads another chunk into the buffer. Reads grow with the buffer, so that a long
+    /// token is re-lexed only a few times.
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        // Drop the text that's already been made into tokens.
+        self.buffer.drain(..self.start);
+        self.offset += self.start;
+        self.start = 0;
+        let mut chunk = vec![0; self.buffer.len().max(CHUNK_SIZE)];
+        let n = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if n == 0 {
+            self.eof = true;
+            if !self.pending.is_empty() {
+                return Err(invalid_utf8());
+            }
+            return Ok(());
+        }
+        self.pending.extend_from_slice(&chunk[..n]);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            // A sequence cut off at the end of the chunk is completed by the next one.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        self.buffer.push_str(std::str::from_utf8(&self.pending[..valid]).unwrap());
+        self.pending.drain(..valid);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = io::Result<OwnedToken>;
+
+    /// Yields the tokens up to, but not including, `EOF`.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(token) if token.kind == TokenKind::EOF => None,
+            result => Some(result),
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
@@ -3,6 +3,9 @@
 
 use std::collections::HashMap;
 
+use super::span::Span;
+use super::trivia::{self, TriviaPiece};
+
 #[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
 pub enum TokenKind {
     Illegal,
@@ -13,56 +16,210 @@ pub enum TokenKind {
     Minus,      // -
     Asterisk,   // *
     Slash,      // /
+    Percent,    // %
+    Exponent,   // **
     Band,       // !
+    Increment,  // ++
+    Decrement,  // --
 
     LT,         // <
     GT,         // >
+    LE,         // <=
+    GE,         // >=
     EQ,         // ==
     NE,         // !=
     SEQ,         // ===
     SNE,         // !==
 
+    And,        // &&
+    Or,         // ||
+    Nullish,    // ??
+
+    BitAnd,     // &
+    BitOr,      // |
+    BitXor,     // ^
+    BitNot,     // ~
+    Shl,        // <<
+    Shr,        // >>
+    UShr,       // >>>
+
+    PlusAssign,     // +=
+    MinusAssign,    // -=
+    AsteriskAssign, // *=
+    SlashAssign,    // /=
+    PercentAssign,  // %=
+    ExponentAssign, // **=
+    ShlAssign,      // <<=
+    ShrAssign,      // >>=
+    UShrAssign,     // >>>=
+    BitAndAssign,   // &=
+    BitOrAssign,    // |=
+    BitXorAssign,   // ^=
+    AndAssign,      // &&=
+    OrAssign,       // ||=
+    NullishAssign,  // ??=
+
     Commas,     // ,
     Semicolon,  // ;
     Dot,        // .
+    Ellipsis,   // ...
+    Question,   // ?
+    QuestionDot,// ?.
+    Colon,      // :
+    Arrow,      // =>
 
     LParen,     // (
     RParen,     // )
     LBrace,     // {
     RBrace,     // }
+    LBracket,   // [
+    RBracket,   // ]
 
+    // Reserved words
     If,
     Else,
     True,
     False,
     Function,
-    Let,
     Return,
+    Var,
+    Const,
+    While,
+    For,
+    Do,
+    Break,
+    Continue,
+    Null,
+    Typeof,
+    Instanceof,
+    In,
+    New,
+    This,
+    Class,
+    Extends,
+    Super,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Switch,
+    Case,
+    Default,
+    Delete,
+    Void,
+    Debugger,
+    With,
+    Enum,
+    Import,
+    Export,
+
+    // Contextual keywords, which are also identifiers where the grammar allows
+    Let,
+    Static,
+    Yield,
+    Await,
+    Async,
+    Of,
+    Get,
+    Set,
+    As,
+    From,
 
     Identifier,
     Number,
+    BigInt,     // 123n
+    String,
+    RegExp,             // /pattern/flags
+    Template,           // `abc` without substitutions
+    TemplateHead,       // `abc${
+    TemplateMiddle,     // }abc${
+    TemplateTail,       // }abc`
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Token<'a> {
     pub kind: TokenKind,
     pub literal: &'a str,
+    pub span: Span,
+    /// Whether a line terminator comes between the previous token and this one.
+    pub newline_before: bool,
+    /// Whitespace and comments between the previous token's trailing trivia and this token.
+    pub leading_trivia: &'a str,
+    /// Whitespace and comments after this token, up to the end of its line.
+    pub trailing_trivia: &'a str,
 }
 
 impl<'a> Token<'a> {
     #[inline(always)]
     pub fn new<'x: 'a>(kind: TokenKind, literal: &'x str) -> Self {
-        return Token{ kind, literal }
+        return Token{ kind, literal, span: Span::default(), newline_before: false, leading_trivia: "", trailing_trivia: "" }
     }
 
     #[inline(always)]
     pub fn new_id<'x: 'a>(id: &'x str) -> Self {
-        return Token{ kind: TokenKind::Identifier, literal: id }
+        return Token{ kind: TokenKind::Identifier, literal: id, span: Span::default(), newline_before: false, leading_trivia: "", trailing_trivia: "" }
     }
 
     #[inline(always)]
     pub fn new_number<'x: 'a>(literal: &'x str) -> Self {
-        return Token{ kind: TokenKind::Number, literal }
+        return Token{ kind: TokenKind::Number, literal, span: Span::default(), newline_before: false, leading_trivia: "", trailing_trivia: "" }
+    }
+
+    /// Whether this token can be an IdentifierReference or a BindingIdentifier.
+    pub fn is_identifier(&self) -> bool {
+        self.kind == TokenKind::Identifier || self.kind.is_contextual_keyword()
+    }
+
+    /// Whether this token can be an IdentifierName, as after `.` or as a property key,
+    /// where reserved words are allowed too.
+    pub fn is_identifier_name(&self) -> bool {
+        self.kind == TokenKind::Identifier || self.kind.is_keyword()
+    }
+
+    pub fn leading_comments(&self) -> impl Iterator<Item = TriviaPiece<'a>> {
+        trivia::pieces(self.leading_trivia).filter(TriviaPiece::is_comment)
+    }
+
+    pub fn trailing_comments(&self) -> impl Iterator<Item = TriviaPiece<'a>> {
+        trivia::pieces(self.trailing_trivia).filter(TriviaPiece::is_comment)
+    }
+}
+
+/// A token that owns its text, for when the source it came from doesn't outlive it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedToken {
+    pub kind: TokenKind,
+    pub literal: String,
+    pub span: Span,
+    pub newline_before: bool,
+    pub leading_trivia: String,
+    pub trailing_trivia: String,
+}
+
+impl OwnedToken {
+    /// Borrows the token back, e.g. to look at its comments.
+    pub fn as_token(&self) -> Token<'_> {
+        Token{
+            kind: self.kind,
+            literal: &self.literal,
+            span: self.span,
+            newline_before: self.newline_before,
+            leading_trivia: &self.leading_trivia,
+            trailing_trivia: &self.trailing_trivia,
+        }
+    }
+}
+
+impl<'a> From<Token<'a>> for OwnedToken {
+    fn from(token: Token<'a>) -> Self {
+        OwnedToken{
+            kind: token.kind,
+            literal: token.literal.to_string(),
+            span: token.span,
+            newline_before: token.newline_before,
+            leading_trivia: token.leading_trivia.to_string(),
+            trailing_trivia: token.trailing_trivia.to_string(),
+        }
     }
 }
 
@@ -72,7 +229,8 @@ impl<'a> From<TokenKind> for Token<'a>  {
     fn from(kind: TokenKind) -> Self {
         debug_assert_ne!(kind, TokenKind::Identifier);
         debug_assert_ne!(kind, TokenKind::Number);
-        return Token { kind, literal: "" }
+        let literal = kind.text().unwrap_or("");
+        return Token { kind, literal, span: Span::default(), newline_before: false, leading_trivia: "", trailing_trivia: "" }
     }
 }
 
@@ -86,8 +244,146 @@ lazy_static! {
         map.insert("true", TokenKind::True);
         map.insert("false", TokenKind::False);
         map.insert("function", TokenKind::Function);
-        map.insert("let", TokenKind::Let);
         map.insert("return", TokenKind::Return);
+        map.insert("var", TokenKind::Var);
+        map.insert("const", TokenKind::Const);
+        map.insert("while", TokenKind::While);
+        map.insert("for", TokenKind::For);
+        map.insert("do", TokenKind::Do);
+        map.insert("break", TokenKind::Break);
+        map.insert("continue", TokenKind::Continue);
+        map.insert("null", TokenKind::Null);
+        map.insert("typeof", TokenKind::Typeof);
+        map.insert("instanceof", TokenKind::Instanceof);
+        map.insert("in", TokenKind::In);
+        map.insert("new", TokenKind::New);
+        map.insert("this", TokenKind::This);
+        map.insert("class", TokenKind::Class);
+        map.insert("extends", TokenKind::Extends);
+        map.insert("super", TokenKind::Super);
+        map.insert("throw", TokenKind::Throw);
+        map.insert("try", TokenKind::Try);
+        map.insert("catch", TokenKind::Catch);
+        map.insert("finally", TokenKind::Finally);
+        map.insert("switch", TokenKind::Switch);
+        map.insert("case", TokenKind::Case);
+        map.insert("default", TokenKind::Default);
+        map.insert("delete", TokenKind::Delete);
+        map.insert("void", TokenKind::Void);
+        map.insert("debugger", TokenKind::Debugger);
+        map.insert("with", TokenKind::With);
+        map.insert("enum", TokenKind::Enum);
+        map.insert("import", TokenKind::Import);
+        map.insert("export", TokenKind::Export);
+
+        map.insert("let", TokenKind::Let);
+        map.insert("static", TokenKind::Static);
+        map.insert("yield", TokenKind::Yield);
+        map.insert("await", TokenKind::Await);
+        map.insert("async", TokenKind::Async);
+        map.insert("of", TokenKind::Of);
+        map.insert("get", TokenKind::Get);
+        map.insert("set", TokenKind::Set);
+        map.insert("as", TokenKind::As);
+        map.insert("from", TokenKind::From);
         map
     };
-}
\ No newline at end of file
+
+    static ref KEYWORD_TEXTS: HashMap<TokenKind, &'static str> = {
+        KEYWORKS.iter().map(|(text, kind)| (*kind, *text)).collect()
+    };
+}
+
+impl TokenKind {
+    /// The text of a keyword or punctuator. Other tokens have no fixed text.
+    pub fn text(self) -> Option<&'static str> {
+        match self {
+            TokenKind::LBrace => Some("{"),
+            TokenKind::RBrace => Some("}"),
+            _ => KEYWORD_TEXTS.get(&self).copied().or_else(|| {
+                PUNCTUATORS.iter().find(|(_, kind)| *kind == self).map(|(text, _)| *text)
+            }),
+        }
+    }
+
+    pub fn is_keyword(self) -> bool {
+        KEYWORD_TEXTS.contains_key(&self)
+    }
+
+    /// Keywords that only have a meaning in some positions, and are identifiers elsewhere.
+    pub fn is_contextual_keyword(self) -> bool {
+        matches!(self,
+            TokenKind::Let | TokenKind::Static | TokenKind::Yield | TokenKind::Await | TokenKind::Async |
+            TokenKind::Of | TokenKind::Get | TokenKind::Set | TokenKind::As | TokenKind::From)
+    }
+}
+
+/// Words that are identifiers in sloppy mode but reserved in strict mode code.
+pub fn is_strict_mode_reserved_word(name: &str) -> bool {
+    matches!(name,
+        "implements" | "interface" | "package" | "private" | "protected" | "public" |
+        "let" | "static" | "yield")
+}
+
+/// Every punctuator but the braces, which the lexer treats specially. Longer ones come
+/// first, so the first one the input starts with is the longest match.
+pub const PUNCTUATORS: &[(&str, TokenKind)] = &[
+    (">>>=", TokenKind::UShrAssign),
+
+    ("===", TokenKind::SEQ),
+    ("!==", TokenKind::SNE),
+    ("**=", TokenKind::ExponentAssign),
+    ("<<=", TokenKind::ShlAssign),
+    (">>=", TokenKind::ShrAssign),
+    (">>>", TokenKind::UShr),
+    ("&&=", TokenKind::AndAssign),
+    ("||=", TokenKind::OrAssign),
+    ("??=", TokenKind::NullishAssign),
+    ("...", TokenKind::Ellipsis),
+
+    ("==", TokenKind::EQ),
+    ("!=", TokenKind::NE),
+    ("<=", TokenKind::LE),
+    (">=", TokenKind::GE),
+    ("&&", TokenKind::And),
+    ("||", TokenKind::Or),
+    ("??", TokenKind::Nullish),
+    ("?.", TokenKind::QuestionDot),
+    ("**", TokenKind::Exponent),
+    ("++", TokenKind::Increment),
+    ("--", TokenKind::Decrement),
+    ("<<", TokenKind::Shl),
+    (">>", TokenKind::Shr),
+    ("=>", TokenKind::Arrow),
+    ("+=", TokenKind::PlusAssign),
+    ("-=", TokenKind::MinusAssign),
+    ("*=", TokenKind::AsteriskAssign),
+    ("/=", TokenKind::SlashAssign),
+    ("%=", TokenKind::PercentAssign),
+    ("&=", TokenKind::BitAndAssign),
+    ("|=", TokenKind::BitOrAssign),
+    ("^=", TokenKind::BitXorAssign),
+
+    ("=", TokenKind::Assign),
+    ("+", TokenKind::Plus),
+    ("-", TokenKind::Minus),
+    ("*", TokenKind::Asterisk),
+    ("/", TokenKind::Slash),
+    ("%", TokenKind::Percent),
+    ("!", TokenKind::Band),
+    ("<", TokenKind::LT),
+    (">", TokenKind::GT),
+    ("&", TokenKind::BitAnd),
+    ("|", TokenKind::BitOr),
+    ("^", TokenKind::BitXor),
+    ("~", TokenKind::BitNot),
+    (",", TokenKind::Commas),
+    (";", TokenKind::Semicolon),
+    (".", TokenKind::Dot),
+    ("?", TokenKind::Question),
+    (":", TokenKind::Colon),
+    ("(", TokenKind::LParen),
+    (")", TokenKind::RParen),
+    ("[", TokenKind::LBracket),
+    ("]", TokenKind::RBracket),
+];
@@ -0,0 +1,55 @@
+use super::is_line_terminator;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,     // including line terminators
+    LineComment,    // // ...
+    BlockComment,   // /* ... */
+    Hashbang,       // #!... at the very start of the input
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TriviaPiece<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+}
+
+impl<'a> TriviaPiece<'a> {
+    pub fn is_comment(&self) -> bool {
+        self.kind != TriviaKind::Whitespace
+    }
+}
+
+/// Splits trivia produced by the lexer into whitespace runs and comments.
+pub fn pieces(trivia: &str) -> Pieces<'_> {
+    Pieces{ rest: trivia }
+}
+
+pub struct Pieces<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = TriviaPiece<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let (kind, len) = if self.rest.starts_with("//") || self.rest.starts_with("#!") {
+            let len = self.rest.find(is_line_terminator).unwrap_or(self.rest.len());
+            let kind = if self.rest.starts_with('#') { TriviaKind::Hashbang } else { TriviaKind::LineComment };
+            (kind, len)
+        } else if let Some(body) = self.rest.strip_prefix("/*") {
+            // An unterminated comment runs to the end.
+            let len = body.find("*/").map_or(self.rest.len(), |end| end + 4);
+            (TriviaKind::BlockComment, len)
+        } else {
+            let len = self.rest.find('/').unwrap_or(self.rest.len());
+            (TriviaKind::Whitespace, len)
+        };
+        let (text, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        return Some(TriviaPiece{ kind, text });
+    }
+}
@@ -1,64 +1,165 @@
 
-use std::collections::HashMap;
 
-use crate::lexer::{token, Lexer};
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer::{escape, token, Checkpoint, Lexer};
 use crate::lexer::token::*;
 use crate::ast::{self, ExpressionStatement, NumberLiteral, PrefixExpression};
+use crate::error::SyntaxError;
+use crate::lexer::span::Span;
 
 mod parser_test;
 
 type PrefixParseFn = fn(&mut Parser) -> Option<Box<dyn ast::Expression>>;
 type InfixParseFn = fn(&mut Parser, Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>>;
+// An expression in parentheses that may turn out to be an arrow function parameter,
+// with its span.
+type CoverElement = (Box<dyn ast::Expression>, Span);
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 enum Precedence {
     Lowest,
-    Equals,         // ==
-    LessGreater,    // > or <
+    Comma,          // a, b
+    Assign,         // = or += and the like
+    Conditional,    // a ? b : c
+    LogicalOr,      // || or ??
+    LogicalAnd,     // &&
+    BitOr,          // |
+    BitXor,         // ^
+    BitAnd,         // &
+    Equality,       // == or != or === or !==
+    Relational,     // < or > or <= or >= or in or instanceof
+    Shift,          // << or >> or >>>
     Sum,            // + or -
-    Product,        // * or /
+    Product,        // * or / or %
+    Exponent,       // **, which is right-associative
     Prefix,         // - or !
+    Postfix,        // a++ or a--
     Call,           // myFunction()
 }
 
+const ASSIGNMENT_OPERATORS: [TokenKind; 16] = [
+    TokenKind::Assign, TokenKind::PlusAssign, TokenKind::MinusAssign, TokenKind::AsteriskAssign,
+    TokenKind::SlashAssign, TokenKind::PercentAssign, TokenKind::ExponentAssign,
+    TokenKind::ShlAssign, TokenKind::ShrAssign, TokenKind::UShrAssign,
+    TokenKind::BitAndAssign, TokenKind::BitOrAssign, TokenKind::BitXorAssign,
+    TokenKind::AndAssign, TokenKind::OrAssign, TokenKind::NullishAssign,
+];
+
 lazy_static! {
     static ref PRECEDENCES: HashMap<TokenKind, Precedence> = {
         let mut map = HashMap::new();
-        map.insert(TokenKind::EQ, Precedence::Equals);
-        map.insert(TokenKind::NE, Precedence::Equals);
-        map.insert(TokenKind::SEQ, Precedence::Equals);
-        map.insert(TokenKind::SNE, Precedence::Equals);
+        map.insert(TokenKind::EQ, Precedence::Equality);
+        map.insert(TokenKind::NE, Precedence::Equality);
+        map.insert(TokenKind::SEQ, Precedence::Equality);
+        map.insert(TokenKind::SNE, Precedence::Equality);
+
+        map.insert(TokenKind::Question, Precedence::Conditional);
+        map.insert(TokenKind::Or, Precedence::LogicalOr);
+        map.insert(TokenKind::Nullish, Precedence::LogicalOr);
+        map.insert(TokenKind::And, Precedence::LogicalAnd);
+        map.insert(TokenKind::BitOr, Precedence::BitOr);
+        map.insert(TokenKind::BitXor, Precedence::BitXor);
+        map.insert(TokenKind::BitAnd, Precedence::BitAnd);
+
+        map.insert(TokenKind::LT, Precedence::Relational);
+        map.insert(TokenKind::GT, Precedence::Relational);
+        map.insert(TokenKind::LE, Precedence::Relational);
+        map.insert(TokenKind::GE, Precedence::Relational);
+        map.insert(TokenKind::In, Precedence::Relational);
+        map.insert(TokenKind::Instanceof, Precedence::Relational);
 
-        map.insert(TokenKind::LT, Precedence::LessGreater);
-        map.insert(TokenKind::GT, Precedence::LessGreater);
+        map.insert(TokenKind::Shl, Precedence::Shift);
+        map.insert(TokenKind::Shr, Precedence::Shift);
+        map.insert(TokenKind::UShr, Precedence::Shift);
 
         map.insert(TokenKind::Plus, Precedence::Sum);
         map.insert(TokenKind::Minus, Precedence::Sum);
         map.insert(TokenKind::Asterisk, Precedence::Product);
         map.insert(TokenKind::Slash, Precedence::Product);
+        map.insert(TokenKind::Percent, Precedence::Product);
+        map.insert(TokenKind::Exponent, Precedence::Exponent);
+
+        map.insert(TokenKind::Commas, Precedence::Comma);
+        for kind in ASSIGNMENT_OPERATORS {
+            map.insert(kind, Precedence::Assign);
+        }
+        map.insert(TokenKind::Increment, Precedence::Postfix);
+        map.insert(TokenKind::Decrement, Precedence::Postfix);
+
+        map.insert(TokenKind::LParen, Precedence::Call);
+        map.insert(TokenKind::Dot, Precedence::Call);
+        map.insert(TokenKind::LBracket, Precedence::Call);
+        map.insert(TokenKind::QuestionDot, Precedence::Call);
+        map.insert(TokenKind::Template, Precedence::Call);
+        map.insert(TokenKind::TemplateHead, Precedence::Call);
         map
     };
 }
 
+/// The declarations seen so far in a block, a function body, or at the top level.
+#[derive(Default)]
+struct Scope {
+    // Whether this is the top level or a function body, where `var` declarations and
+    // functions belong.
+    function: bool,
+    // Names bound by `let`, `const`, and functions declared in a block, in order.
+    lexical: Vec<String>,
+    // Names bound by `var` here or in a nested block, since they are hoisted, and
+    // parameters and functions declared directly in a function scope.
+    vars: HashSet<String>,
+    // Labels of the statements being parsed, with where their bodies start.
+    labels: Vec<(String, usize)>,
+    // Where the loops being parsed start.
+    loops: Vec<usize>,
+}
+
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
 
     current_token: token::Token<'a>,
     peak_token: token::Token<'a>,
+    // Where the lexer was before reading each token, for re-scanning it.
+    current_checkpoint: Checkpoint,
+    peak_checkpoint: Checkpoint,
 
-    errors: Vec<String>,
+    errors: Vec<SyntaxError>,
+    // Set by a "use strict" directive.
+    strict: bool,
+    // The innermost scope is last.
+    scopes: Vec<Scope>,
+    // Where the left-hand side passed to an infix parse function starts.
+    lhs_start: usize,
+    // Set in the head of a `for` loop, where `in` would start a for-in loop instead.
+    no_in: bool,
+    // Set in the body of an async function, where `await` is an operator.
+    in_async: bool,
+    // Spans of shorthand properties with an initializer, like `a = 1` in `{a = 1}`, in
+    // the current statement. They're only valid if the object becomes a pattern.
+    cover_initializers: Vec<Span>,
 
     prefix_parse_fns: HashMap<TokenKind, PrefixParseFn>,
     infix_parse_fns: HashMap<TokenKind, InfixParseFn>,
 }
 
 impl<'a> Parser<'a> {
+    // Only the tests drive the parser so far; the REPL still prints tokens.
+    #[allow(dead_code)]
     pub fn new(lexer: Lexer<'a>) -> Self {
+        let checkpoint = lexer.checkpoint();
         let mut parser = Parser{
-            lexer: lexer,
+            lexer,
             current_token: TokenKind::EOF.into(),
             peak_token: TokenKind::EOF.into(),
+            current_checkpoint: checkpoint.clone(),
+            peak_checkpoint: checkpoint,
             errors: Vec::new(),
+            strict: false,
+            scopes: vec![Scope{ function: true, ..Default::default() }],
+            lhs_start: 0,
+            no_in: false,
+            in_async: false,
+            cover_initializers: Vec::new(),
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
         };
@@ -66,28 +167,74 @@ impl<'a> Parser<'a> {
         parser.next_token();
 
         parser.prefix_parse_fns.insert(TokenKind::Identifier, Self::parse_identifier);
+        for kind in [
+            TokenKind::Let, TokenKind::Static, TokenKind::Yield, TokenKind::Await, TokenKind::Async,
+            TokenKind::Of, TokenKind::Get, TokenKind::Set, TokenKind::As, TokenKind::From,
+        ] {
+            parser.prefix_parse_fns.insert(kind, Self::parse_identifier);
+        }
         parser.prefix_parse_fns.insert(TokenKind::Number, Self::parse_number);
-        parser.prefix_parse_fns.insert(TokenKind::Minus, Self::parse_prefix_expression);
-        parser.prefix_parse_fns.insert(TokenKind::Band, Self::parse_prefix_expression);
-
-        parser.infix_parse_fns.insert(TokenKind::Plus, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::Minus, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::Asterisk, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::Slash, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::LT, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::GT, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::EQ, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::NE, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::SEQ, Self::parse_infix_expression);
-        parser.infix_parse_fns.insert(TokenKind::SNE, Self::parse_infix_expression);
+        parser.prefix_parse_fns.insert(TokenKind::BigInt, Self::parse_bigint);
+        parser.prefix_parse_fns.insert(TokenKind::String, Self::parse_string);
+        parser.prefix_parse_fns.insert(TokenKind::True, Self::parse_boolean);
+        parser.prefix_parse_fns.insert(TokenKind::False, Self::parse_boolean);
+        parser.prefix_parse_fns.insert(TokenKind::Null, Self::parse_null);
+        parser.prefix_parse_fns.insert(TokenKind::LParen, Self::parse_grouped_expression);
+        parser.prefix_parse_fns.insert(TokenKind::Function, Self::parse_function_expression);
+        parser.prefix_parse_fns.insert(TokenKind::LBrace, Self::parse_object_literal);
+        parser.prefix_parse_fns.insert(TokenKind::LBracket, Self::parse_array_literal);
+        parser.prefix_parse_fns.insert(TokenKind::Slash, Self::parse_regexp);
+        parser.prefix_parse_fns.insert(TokenKind::SlashAssign, Self::parse_regexp);
+        parser.prefix_parse_fns.insert(TokenKind::Template, Self::parse_template_literal);
+        parser.prefix_parse_fns.insert(TokenKind::TemplateHead, Self::parse_template_literal);
+        for kind in [
+            TokenKind::Minus, TokenKind::Plus, TokenKind::Band, TokenKind::BitNot,
+            TokenKind::Typeof, TokenKind::Void, TokenKind::Delete,
+        ] {
+            parser.prefix_parse_fns.insert(kind, Self::parse_prefix_expression);
+        }
+        parser.prefix_parse_fns.insert(TokenKind::Increment, Self::parse_prefix_update);
+        parser.prefix_parse_fns.insert(TokenKind::Decrement, Self::parse_prefix_update);
+
+        for kind in [
+            TokenKind::Plus, TokenKind::Minus, TokenKind::Asterisk, TokenKind::Slash,
+            TokenKind::Percent, TokenKind::Exponent,
+            TokenKind::LT, TokenKind::GT, TokenKind::LE, TokenKind::GE,
+            TokenKind::EQ, TokenKind::NE, TokenKind::SEQ, TokenKind::SNE,
+            TokenKind::Shl, TokenKind::Shr, TokenKind::UShr,
+            TokenKind::BitAnd, TokenKind::BitOr, TokenKind::BitXor,
+            TokenKind::In, TokenKind::Instanceof,
+        ] {
+            parser.infix_parse_fns.insert(kind, Self::parse_infix_expression);
+        }
+        for kind in [TokenKind::And, TokenKind::Or, TokenKind::Nullish] {
+            parser.infix_parse_fns.insert(kind, Self::parse_logical_expression);
+        }
+        parser.infix_parse_fns.insert(TokenKind::Question, Self::parse_conditional_expression);
+        parser.infix_parse_fns.insert(TokenKind::Commas, Self::parse_sequence_expression);
+        for kind in ASSIGNMENT_OPERATORS {
+            parser.infix_parse_fns.insert(kind, Self::parse_assignment_expression);
+        }
+        parser.infix_parse_fns.insert(TokenKind::Increment, Self::parse_postfix_update);
+        parser.infix_parse_fns.insert(TokenKind::Decrement, Self::parse_postfix_update);
+        parser.infix_parse_fns.insert(TokenKind::LParen, Self::parse_call_expression);
+        parser.infix_parse_fns.insert(TokenKind::Dot, Self::parse_member_expression);
+        parser.infix_parse_fns.insert(TokenKind::LBracket, Self::parse_member_expression);
+        parser.infix_parse_fns.insert(TokenKind::QuestionDot, Self::parse_optional_chain);
+        parser.infix_parse_fns.insert(TokenKind::Template, Self::parse_tagged_template);
+        parser.infix_parse_fns.insert(TokenKind::TemplateHead, Self::parse_tagged_template);
         return parser;
     }
 
-    pub fn errors(&self) -> &Vec<String> { &self.errors }
+    #[allow(dead_code)]
+    pub fn errors(&self) -> &Vec<SyntaxError> { &self.errors }
 
+    #[allow(dead_code)]
     pub fn parse_program(&mut self) -> ast::Program {
         let mut statements = Vec::new();
-        while self.peak_token.kind != TokenKind::EOF {
+        let mut prologue = true;
+        while self.current_token.kind != TokenKind::EOF {
+            self.check_directive(&mut prologue);
             if let Some(statement) = self.parse_statement() {
                 statements.push(statement);
             }
@@ -96,64 +243,638 @@ impl<'a> Parser<'a> {
         return ast::Program{ statements };
     }
 
+    /// Looks for a "use strict" directive while still in the directive prologue at the
+    /// start of the program or a function body.
+    fn check_directive(&mut self, prologue: &mut bool) {
+        if *prologue {
+            *prologue = self.current_is_directive();
+            if *prologue && is_use_strict(self.current_token.literal) {
+                self.strict = true;
+            }
+        }
+    }
+
+    /// Whether the current token is a string literal that makes up a whole statement,
+    /// which is a directive in a prologue.
+    fn current_is_directive(&self) -> bool {
+        if self.current_token.kind != TokenKind::String {
+            return false;
+        }
+        match self.peak_token.kind {
+            TokenKind::Semicolon | TokenKind::RBrace | TokenKind::EOF => true,
+            _ => self.peak_token.newline_before && self.peak_precedence() == Precedence::Lowest,
+        }
+    }
+
+    /// Parses the statement starting at the current token, leaving the parser on its
+    /// last token.
     pub fn parse_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let outer = std::mem::take(&mut self.cover_initializers);
+        let statement = self.parse_statement_kind();
+        // Whatever wasn't made into a pattern by the end of the statement never will be.
+        let pending = std::mem::replace(&mut self.cover_initializers, outer);
+        if statement.is_some() {
+            for span in pending {
+                self.error("invalid shorthand property initializer", span);
+            }
+        }
+        return statement;
+    }
+
+    fn parse_statement_kind(&mut self) -> Option<Box<dyn ast::Statement>> {
         match self.current_token.kind {
-            TokenKind::Let => self.parse_let_statement(),
+            _ if self.current_token.is_identifier() && self.peak_token.kind == TokenKind::Colon => {
+                self.parse_labeled_statement()
+            }
+            TokenKind::Let if self.peak_starts_let_declaration() => self.parse_variable_declaration(),
+            TokenKind::Const | TokenKind::Var => self.parse_variable_declaration(),
             TokenKind::Return => self.parse_return_statement(),
+            TokenKind::Throw => self.parse_throw_statement(),
+            TokenKind::LBrace => self.parse_block_statement(),
+            TokenKind::If => self.parse_if_statement(),
+            TokenKind::Function => self.parse_function_declaration(),
+            TokenKind::Async if self.peak_starts_async_function() => self.parse_function_declaration(),
+            TokenKind::While => self.parse_while_statement(),
+            TokenKind::Do => self.parse_do_while_statement(),
+            TokenKind::For => self.parse_for_statement(),
+            TokenKind::Break | TokenKind::Continue => self.parse_break_or_continue_statement(),
+            TokenKind::Semicolon => Some(Box::new(ast::EmptyStatement{})),
             _ => self.parse_expression_statement(),
         }
     }
 
-    fn parse_let_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
-        if !self.expect_peak(TokenKind::Identifier) {
-            return None;
+    /// `let` is only a keyword when a binding follows it; otherwise it is an identifier.
+    fn peak_starts_let_declaration(&self) -> bool {
+        self.peak_token.is_identifier()
+            || matches!(self.peak_token.kind, TokenKind::LBracket | TokenKind::LBrace)
+    }
+
+    /// Parses a `let`, `const` or `var` declaration list.
+    fn parse_variable_declaration(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let (declaration, spans) = self.parse_declaration_list()?;
+        self.check_const_initializers(&declaration, &spans);
+        self.consume_semicolon();
+        return Some(Box::new(declaration));
+    }
+
+    /// Parses the declarators after the current `let`, `const` or `var`, leaving out the
+    /// `;`. Returns the declaration with the spans of the declared names.
+    fn parse_declaration_list(&mut self) -> Option<(ast::VariableDeclaration, Vec<Span>)> {
+        let kind = self.current_token.kind;
+        let mut declarations = Vec::new();
+        let mut spans = Vec::new();
+        loop {
+            if !self.expect_peak_identifier() {
+                return None;
+            }
+            let span = self.current_token.span;
+            let name = identifier_name(self.current_token.literal);
+            if name == "let" && kind != TokenKind::Var {
+                self.error("'let' cannot be a lexically bound name", span);
+            }
+            self.check_identifier(&name, span);
+            self.declare(kind, &name, span);
+            let init = if self.peak_token.kind == TokenKind::Assign {
+                self.next_token();
+                self.next_token();
+                Some(self.parse_expression(Precedence::Comma)?)
+            } else {
+                None
+            };
+            declarations.push(ast::VariableDeclarator{ id: ast::Identifier{ name }, init });
+            spans.push(span);
+            if self.peak_token.kind != TokenKind::Commas {
+                break;
+            }
+            self.next_token();
+        }
+        return Some((ast::VariableDeclaration{ kind, declarations }, spans));
+    }
+
+    /// A `const` needs an initializer, except as the variable of a `for-in` or `for-of`.
+    fn check_const_initializers(&mut self, declaration: &ast::VariableDeclaration, spans: &[Span]) {
+        if declaration.kind != TokenKind::Const {
+            return;
         }
-        let id = ast::Identifier {
-            name: self.current_token.literal.to_string()
+        for (declarator, span) in declaration.declarations.iter().zip(spans) {
+            if declarator.init.is_none() {
+                self.error("missing initializer in const declaration", *span);
+            }
+        }
+    }
+
+    /// Records a declaration in the current scope, reporting it if it clashes with
+    /// another one.
+    fn declare(&mut self, kind: TokenKind, name: &str, span: Span) {
+        let function_scope = self.scopes.last().unwrap().function;
+        let clash = if kind == TokenKind::Var {
+            // A `var` belongs to the enclosing function, or the whole program, so it
+            // can't share a name with a `let` or `const` in any block it is in.
+            let mut clash = false;
+            for scope in self.scopes.iter_mut().rev() {
+                clash |= scope.lexical.iter().any(|n| n == name);
+                scope.vars.insert(name.to_string());
+                if scope.function {
+                    break;
+                }
+            }
+            clash
+        } else if kind == TokenKind::Function && function_scope {
+            // Functions at the top of a function body, or of the program, act like `var`.
+            let scope = self.scopes.last_mut().unwrap();
+            scope.vars.insert(name.to_string());
+            scope.lexical.iter().any(|n| n == name)
+        } else {
+            let scope = self.scopes.last_mut().unwrap();
+            let clash = scope.lexical.iter().any(|n| n == name) || scope.vars.contains(name);
+            scope.lexical.push(name.to_string());
+            clash
         };
-        if !self.expect_peak(TokenKind::Assign) {
+        if clash {
+            self.error(format!("'{}' has already been declared", name), span);
+        }
+    }
+
+    fn parse_block_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let block = self.parse_block()?;
+        return Some(Box::new(block));
+    }
+
+    /// Parses the statements between the current `{` and its `}`, in a scope of their own.
+    fn parse_block(&mut self) -> Option<ast::BlockStatement> {
+        self.scopes.push(Scope::default());
+        let statements = self.parse_statement_list();
+        let scope = self.scopes.pop().unwrap();
+        return Some(ast::BlockStatement{ statements: statements?, lexical_names: scope.lexical });
+    }
+
+    /// Parses statements from the current `{` up to its `}`, in the innermost scope.
+    fn parse_statement_list(&mut self) -> Option<Vec<Box<dyn ast::Statement>>> {
+        let mut statements = Vec::new();
+        // Only a function body can start with directives.
+        let mut prologue = self.scopes.last().unwrap().function;
+        while self.peak_token.kind != TokenKind::RBrace {
+            if self.peak_token.kind == TokenKind::EOF {
+                self.peak_error(TokenKind::RBrace);
+                return None;
+            }
+            self.next_token();
+            self.check_directive(&mut prologue);
+            if let Some(statement) = self.parse_statement() {
+                statements.push(statement);
+            }
+        }
+        self.next_token();
+        return Some(statements);
+    }
+
+    fn parse_function_declaration(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let is_async = self.current_token.kind == TokenKind::Async;
+        if is_async {
+            self.next_token();
+        }
+        if !self.peak_token.is_identifier() {
+            self.peak_error(TokenKind::Identifier);
             return None;
         }
-        // TODO: parse assignee
-        while self.current_token.kind != TokenKind::Semicolon {
+        let function = self.parse_function(true, is_async)?;
+        return Some(Box::new(ast::FunctionDeclaration{ function }));
+    }
+
+    /// `async` starts an async function only if `function` follows on the same line.
+    fn peak_starts_async_function(&self) -> bool {
+        self.peak_token.kind == TokenKind::Function && !self.peak_token.newline_before
+    }
+
+    /// Parses a function from the current `function` keyword to the `}` closing its body.
+    /// The name of a declaration is bound in the enclosing scope.
+    fn parse_function(&mut self, declaration: bool, is_async: bool) -> Option<ast::FunctionLiteral> {
+        let mut name = None;
+        if self.peak_token.is_identifier() {
             self.next_token();
+            let span = self.current_token.span;
+            let id = ast::Identifier{ name: identifier_name(self.current_token.literal) };
+            self.check_identifier(&id.name, span);
+            if declaration {
+                self.declare(TokenKind::Function, &id.name, span);
+            }
+            name = Some(id);
         }
-        let s = ast::LetStatement{
-            id,
-            value: Box::new(ast::NoOpExpression{})
+        return self.parse_function_rest(name, is_async);
+    }
+
+    /// Parses the parameters and body of a function, from the token before its `(`.
+    fn parse_function_rest(&mut self, name: Option<ast::Identifier>, is_async: bool) -> Option<ast::FunctionLiteral> {
+        if !self.expect_peak(TokenKind::LParen) {
+            return None;
+        }
+        let strict = self.strict;
+        let no_in = std::mem::replace(&mut self.no_in, false);
+        let in_async = std::mem::replace(&mut self.in_async, is_async);
+        self.scopes.push(Scope{ function: true, ..Default::default() });
+        let params = self.parse_parameters();
+        let body = match params {
+            Some(_) if self.expect_peak(TokenKind::LBrace) => self.parse_statement_list(),
+            _ => None,
         };
-        return Some(Box::new(s));
+        self.no_in = no_in;
+        self.in_async = in_async;
+        let scope = self.scopes.pop().unwrap();
+        let (params, spans) = params?;
+        let body = ast::BlockStatement{ statements: body?, lexical_names: scope.lexical };
+        // Whether a duplicate is allowed depends on a "use strict" in the body.
+        self.check_parameters(&params, &spans, false);
+        self.strict = strict;
+        return Some(ast::FunctionLiteral{ name, params, body, is_async });
     }
 
-    fn parse_return_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+    /// Parses a parameter list from the current `(` to its `)`, declaring the names in
+    /// the innermost scope. Returns the parameters and the spans of their names.
+    fn parse_parameters(&mut self) -> Option<(Vec<ast::Parameter>, Vec<Span>)> {
+        let mut params = Vec::new();
+        let mut spans = Vec::new();
+        while self.peak_token.kind != TokenKind::RParen {
+            let rest = self.peak_token.kind == TokenKind::Ellipsis;
+            if rest {
+                self.next_token();
+            }
+            if !self.expect_peak_identifier() {
+                return None;
+            }
+            let span = self.current_token.span;
+            let id = ast::Identifier{ name: identifier_name(self.current_token.literal) };
+            self.check_identifier(&id.name, span);
+            self.declare(TokenKind::Var, &id.name, span);
+            let default = if !rest && self.peak_token.kind == TokenKind::Assign {
+                self.next_token();
+                self.next_token();
+                Some(self.parse_expression(Precedence::Comma)?)
+            } else {
+                None
+            };
+            params.push(ast::Parameter{ id, default, rest });
+            spans.push(span);
+            if rest {
+                if self.peak_token.kind != TokenKind::RParen {
+                    self.error("rest parameter must be last formal parameter", self.peak_token.span);
+                    return None;
+                }
+            } else if self.peak_token.kind != TokenKind::RParen && !self.expect_peak(TokenKind::Commas) {
+                return None;
+            }
+        }
+        self.next_token();
+        return Some((params, spans));
+    }
+
+    /// Reports duplicate parameter names, which are only allowed in a sloppy mode
+    /// function, not an arrow, with a plain list of names.
+    fn check_parameters(&mut self, params: &[ast::Parameter], spans: &[Span], arrow: bool) {
+        let simple = params.iter().all(|p| p.default.is_none() && !p.rest);
+        if simple && !self.strict && !arrow {
+            return;
+        }
+        for (index, param) in params.iter().enumerate() {
+            if params[..index].iter().any(|p| p.id.name == param.id.name) {
+                self.error("duplicate parameter name not allowed in this context", spans[index]);
+            }
+        }
+    }
+
+    fn parse_if_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let test = self.parse_parenthesized_condition()?;
         self.next_token();
-        // TODO: parse expression
-        while self.current_token.kind != TokenKind::Semicolon {
+        let consequent = self.parse_substatement()?;
+        // An `else` goes with the nearest `if`, which has already taken it by now if
+        // there is one nested in `consequent`.
+        let alternate = if self.peak_token.kind == TokenKind::Else {
+            self.next_token();
             self.next_token();
+            Some(self.parse_substatement()?)
+        } else {
+            None
+        };
+        return Some(Box::new(ast::IfStatement{ test, consequent, alternate }));
+    }
+
+    fn parse_labeled_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let span = self.current_token.span;
+        let label = ast::Identifier{ name: identifier_name(self.current_token.literal) };
+        self.check_identifier(&label.name, span);
+        if self.find_label(&label.name).is_some() {
+            self.error(format!("label '{}' has already been declared", label.name), span);
+        }
+        self.next_token();
+        self.next_token();
+        let start = self.current_token.span.start;
+        let labels = &mut self.scopes.last_mut().unwrap().labels;
+        // In `a: b: while (c) continue a;` both labels denote the loop.
+        for (_, body_start) in labels.iter_mut().filter(|(_, body_start)| *body_start == span.start) {
+            *body_start = start;
         }
-        let s = ast::ReturnStatement{
-            value: Box::new(ast::NoOpExpression{})
+        labels.push((label.name.clone(), start));
+        let body = self.parse_substatement();
+        self.scopes.last_mut().unwrap().labels.pop();
+        return Some(Box::new(ast::LabeledStatement{ label, body: body? }));
+    }
+
+    /// Where the body of the enclosing statement labeled `name` starts. Labels can't
+    /// be seen from inside a function.
+    fn find_label(&self, name: &str) -> Option<usize> {
+        for scope in self.scopes.iter().rev() {
+            if let Some((_, start)) = scope.labels.iter().rev().find(|(n, _)| n == name) {
+                return Some(*start);
+            }
+            if scope.function {
+                break;
+            }
+        }
+        return None;
+    }
+
+    /// Whether a loop starting at `start`, or any loop if `None`, encloses the current
+    /// statement.
+    fn in_loop(&self, start: Option<usize>) -> bool {
+        for scope in self.scopes.iter().rev() {
+            if scope.loops.iter().any(|s| start.is_none_or(|start| *s == start)) {
+                return true;
+            }
+            if scope.function {
+                break;
+            }
+        }
+        return false;
+    }
+
+    /// Parses the body of the loop starting at `start`, from the token before it.
+    fn parse_loop_body(&mut self, start: usize) -> Option<Box<dyn ast::Statement>> {
+        self.next_token();
+        self.scopes.last_mut().unwrap().loops.push(start);
+        let body = self.parse_substatement();
+        self.scopes.last_mut().unwrap().loops.pop();
+        return body;
+    }
+
+    fn parse_while_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let start = self.current_token.span.start;
+        let test = self.parse_parenthesized_condition()?;
+        let body = self.parse_loop_body(start)?;
+        return Some(Box::new(ast::WhileStatement{ test, body }));
+    }
+
+    fn parse_do_while_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let start = self.current_token.span.start;
+        let body = self.parse_loop_body(start)?;
+        if !self.expect_peak(TokenKind::While) {
+            return None;
+        }
+        let test = self.parse_parenthesized_condition()?;
+        // A `;` is inserted after a do-while statement even without a line break.
+        if self.peak_token.kind == TokenKind::Semicolon {
+            self.next_token();
+        }
+        return Some(Box::new(ast::DoWhileStatement{ body, test }));
+    }
+
+    /// Parses `(expression)` after the current `while` or `if`.
+    fn parse_parenthesized_condition(&mut self) -> Option<Box<dyn ast::Expression>> {
+        if !self.expect_peak(TokenKind::LParen) {
+            return None;
+        }
+        self.next_token();
+        let test = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peak(TokenKind::RParen) {
+            return None;
+        }
+        return Some(test);
+    }
+
+    fn parse_for_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let start = self.current_token.span.start;
+        if !self.expect_peak(TokenKind::LParen) {
+            return None;
+        }
+        // `let` and `const` in the head are scoped to the loop.
+        self.scopes.push(Scope::default());
+        let statement = self.parse_for_rest(start);
+        self.scopes.pop();
+        return statement;
+    }
+
+    /// Parses a `for` statement from its `(`.
+    fn parse_for_rest(&mut self, start: usize) -> Option<Box<dyn ast::Statement>> {
+        self.next_token();
+        let init_start = self.current_token.span.start;
+        let mut spans = Vec::new();
+        self.no_in = true;
+        let init = match self.current_token.kind {
+            TokenKind::Semicolon => Some(None),
+            TokenKind::Let if !self.peak_starts_let_declaration() => {
+                self.parse_expression(Precedence::Lowest).map(|e| Some(ast::ForInit::Expression(e)))
+            }
+            TokenKind::Let | TokenKind::Const | TokenKind::Var => {
+                self.parse_declaration_list().map(|(declaration, declaration_spans)| {
+                    spans = declaration_spans;
+                    Some(ast::ForInit::Declaration(declaration))
+                })
+            }
+            _ => self.parse_expression(Precedence::Lowest).map(|e| Some(ast::ForInit::Expression(e))),
+        };
+        self.no_in = false;
+        let init = init?;
+        let init_span = Span::new(init_start, self.current_token.span.end);
+
+        if let (Some(left), TokenKind::In | TokenKind::Of) = (&init, self.peak_token.kind) {
+            let of = self.peak_token.kind == TokenKind::Of;
+            self.check_for_in_of_left(left, init_span, of);
+            self.next_token();
+            self.next_token();
+            // for-of takes a single assignment expression, for-in a whole expression.
+            let precedence = if of { Precedence::Comma } else { Precedence::Lowest };
+            let right = self.parse_expression(precedence)?;
+            if !self.expect_peak(TokenKind::RParen) {
+                return None;
+            }
+            let body = self.parse_loop_body(start)?;
+            let left = init.unwrap();
+            if of {
+                return Some(Box::new(ast::ForOfStatement{ left, right, body }));
+            }
+            return Some(Box::new(ast::ForInStatement{ left, right, body }));
+        }
+
+        if let Some(ast::ForInit::Declaration(declaration)) = &init {
+            self.check_const_initializers(declaration, &spans);
+        }
+        if init.is_some() && !self.expect_peak(TokenKind::Semicolon) {
+            return None;
+        }
+        let test = if self.peak_token.kind == TokenKind::Semicolon {
+            None
+        } else {
+            self.next_token();
+            Some(self.parse_expression(Precedence::Lowest)?)
+        };
+        if !self.expect_peak(TokenKind::Semicolon) {
+            return None;
+        }
+        let update = if self.peak_token.kind == TokenKind::RParen {
+            None
+        } else {
+            self.next_token();
+            Some(self.parse_expression(Precedence::Lowest)?)
         };
-        return Some(Box::new(s));
+        if !self.expect_peak(TokenKind::RParen) {
+            return None;
+        }
+        let body = self.parse_loop_body(start)?;
+        return Some(Box::new(ast::ForStatement{ init, test, update, body }));
+    }
+
+    /// The variable of a `for-in` or `for-of` loop is a single binding without an
+    /// initializer, or something that can be assigned to.
+    fn check_for_in_of_left(&mut self, left: &ast::ForInit, span: Span, of: bool) {
+        let name = if of { "for-of" } else { "for-in" };
+        match left {
+            ast::ForInit::Declaration(declaration) => {
+                if declaration.declarations.len() != 1 {
+                    self.error(format!("only one variable can be declared in a {} loop", name), span);
+                } else if declaration.declarations[0].init.is_some() {
+                    self.error(format!("{} loop variable declaration may not have an initializer", name), span);
+                }
+            }
+            ast::ForInit::Expression(e) => {
+                if let Some(span) = invalid_target_span(&**e, span) {
+                    self.error(format!("invalid left-hand side in {} loop", name), span);
+                }
+                self.settle_cover_initializers(&**e);
+            }
+        }
+    }
+
+    fn parse_break_or_continue_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let keyword = self.current_token;
+        let is_break = keyword.kind == TokenKind::Break;
+        // Like `return`, a line break right after the keyword ends the statement.
+        let label = if self.peak_token.is_identifier() && !self.peak_token.newline_before {
+            self.next_token();
+            Some(ast::Identifier{ name: identifier_name(self.current_token.literal) })
+        } else {
+            None
+        };
+        let span = Span::new(keyword.span.start, self.current_token.span.end);
+        match &label {
+            Some(label) => match self.find_label(&label.name) {
+                None => self.error(format!("undefined label '{}'", label.name), self.current_token.span),
+                Some(start) if !is_break && !self.in_loop(Some(start)) => {
+                    let msg = format!("illegal continue statement: '{}' does not denote an iteration statement", label.name);
+                    self.error(msg, span);
+                }
+                Some(_) => {}
+            },
+            None if !self.in_loop(None) => {
+                let msg = if is_break { "illegal break statement" } else { "illegal continue statement: no surrounding iteration statement" };
+                self.error(msg, span);
+            }
+            None => {}
+        }
+        self.consume_semicolon();
+        if is_break {
+            return Some(Box::new(ast::BreakStatement{ label }));
+        }
+        return Some(Box::new(ast::ContinueStatement{ label }));
+    }
+
+    /// Parses the body of a statement such as `if`, which can't be a `let` or `const`
+    /// declaration on its own, since there would be no block for it to be scoped to.
+    fn parse_substatement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        let lexical = match self.current_token.kind {
+            TokenKind::Const => true,
+            TokenKind::Let => self.peak_starts_let_declaration(),
+            _ => false,
+        };
+        if lexical {
+            self.error("lexical declaration cannot appear in a single-statement context",
+                self.current_token.span);
+        }
+        return self.parse_statement();
+    }
+
+    fn parse_return_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        // `return` is a restricted production: a line break right after it ends the
+        // statement.
+        let value = if self.at_statement_end() {
+            None
+        } else {
+            self.next_token();
+            Some(self.parse_expression(Precedence::Lowest)?)
+        };
+        self.consume_semicolon();
+        return Some(Box::new(ast::ReturnStatement{ value }));
+    }
+
+    fn parse_throw_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
+        // Unlike `return`, a `throw` needs a value, so a line break right after it is
+        // an error rather than the end of the statement.
+        if self.peak_token.newline_before {
+            self.error("illegal newline after throw", self.current_token.span);
+            return None;
+        }
+        self.next_token();
+        let argument = self.parse_expression(Precedence::Lowest)?;
+        self.consume_semicolon();
+        return Some(Box::new(ast::ThrowStatement{ argument }));
     }
 
     fn parse_expression_statement(&mut self) -> Option<Box<dyn ast::Statement>> {
         if let Some(expression) = self.parse_expression(Precedence::Lowest) {
-            if self.peak_token.kind == TokenKind::Semicolon {
-                self.next_token();
-            }
+            self.consume_semicolon();
             return Some( Box::new(ExpressionStatement{ expression }));
         }
         return None;
     }
 
+    /// Whether the statement can end at the current token, because the peak token is
+    /// a `;`, or one could be inserted before it.
+    fn at_statement_end(&self) -> bool {
+        match self.peak_token.kind {
+            TokenKind::Semicolon | TokenKind::RBrace | TokenKind::EOF => true,
+            _ => self.peak_token.newline_before,
+        }
+    }
+
+    /// Ends the statement at the current token, moving onto its `;` if there is one.
+    /// Following the automatic semicolon insertion rules, the `;` can be left out
+    /// before a `}`, at the end of the input, or before a line break.
+    fn consume_semicolon(&mut self) -> bool {
+        if self.peak_token.kind == TokenKind::Semicolon {
+            self.next_token();
+            return true;
+        }
+        if self.at_statement_end() {
+            return true;
+        }
+        self.peak_error(TokenKind::Semicolon);
+        return false;
+    }
+
     fn parse_expression(&mut self, precedence: Precedence) -> Option<Box<dyn ast::Expression>> {
         if let Some(prefix_fn) = self.prefix_parse_fns.get(&self.current_token.kind) {
-            let mut lhs = prefix_fn(self);
-            if let None = lhs {
+            let start = self.current_token.span.start;
+            let mut lhs = prefix_fn(self)?;
+            // An arrow function is a whole assignment expression: it can't be an operand,
+            // and only a `,` can follow it.
+            let arrow = lhs.is::<ast::ArrowFunction>();
+            if arrow && precedence >= Precedence::Assign {
+                let span = Span::new(start, self.current_token.span.end);
+                self.error("malformed arrow function parameter list", span);
                 return None;
             }
             while self.peak_token.kind != TokenKind::Semicolon && precedence < self.peak_precedence() {
+                if self.peak_is_restricted() || (arrow && self.peak_token.kind != TokenKind::Commas) {
+                    break;
+                }
+                self.lhs_start = start;
                 if let Some(infix_fn) = self.infix_parse_fns.get(&self.peak_token.kind) {
                     // If run the following statement, rust will say that `self is borrowed as immutable
                     // in the self.infix_parse_fns.get(...)`.
@@ -161,68 +882,566 @@ impl<'a> Parser<'a> {
                     // rust will not complain anything.
                     // I think it must be a bug of rustc. Anyway, just call it in the infix_fn for now.
                     // self.next_token();
-                    lhs = infix_fn(self, lhs.unwrap());
+                    lhs = infix_fn(self, lhs)?;
                 } else {
-                    return lhs;
+                    return Some(lhs);
                 }
             }
-            return lhs;
+            return Some(lhs);
         }
         self.no_prefix_error(self.current_token.kind);
         return None;
     }
 
+    /// Parses an expression inside brackets, where `in` is an operator even in the head
+    /// of a `for` loop.
+    fn parse_nested_expression(&mut self, precedence: Precedence) -> Option<Box<dyn ast::Expression>> {
+        let no_in = std::mem::replace(&mut self.no_in, false);
+        let expression = self.parse_expression(precedence);
+        self.no_in = no_in;
+        return expression;
+    }
+
+    /// Postfix `++` and `--` are restricted productions: on a new line they can only
+    /// start the next statement.
+    fn peak_is_restricted(&self) -> bool {
+        self.peak_token.newline_before
+            && matches!(self.peak_token.kind, TokenKind::Increment | TokenKind::Decrement)
+    }
+
     fn parse_identifier(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
-        let id = ast::Identifier{ name: parser.current_token.literal.to_string() };
+        if parser.current_token.kind == TokenKind::Await && parser.in_async {
+            return Self::parse_prefix_expression(parser);
+        }
+        let span = parser.current_token.span;
+        let id = ast::Identifier{ name: identifier_name(parser.current_token.literal) };
+        parser.check_identifier(&id.name, span);
+        match parser.peak_token.kind {
+            TokenKind::Arrow => return parser.parse_arrow_function(vec![(Box::new(id), span)], false),
+            _ if parser.current_token.kind == TokenKind::Async && !parser.peak_token.newline_before => {
+                return parser.parse_async_arrow_function(id);
+            }
+            _ => {}
+        }
         return Some(Box::new(id));
     }
 
+    /// Parses what follows `async`: a function expression, an arrow function, a call to
+    /// a function named `async`, or nothing if it is just an identifier.
+    fn parse_async_arrow_function(&mut self, id: ast::Identifier) -> Option<Box<dyn ast::Expression>> {
+        if self.peak_starts_async_function() {
+            self.next_token();
+            let function = self.parse_function(false, true)?;
+            return Some(Box::new(function));
+        }
+        if self.peak_token.is_identifier() {
+            self.next_token();
+            let span = self.current_token.span;
+            let param = ast::Identifier{ name: identifier_name(self.current_token.literal) };
+            self.check_identifier(&param.name, span);
+            if self.peak_token.kind != TokenKind::Arrow {
+                self.peak_error(TokenKind::Arrow);
+                return None;
+            }
+            return self.parse_arrow_function(vec![(Box::new(param), span)], true);
+        }
+        if self.peak_token.kind == TokenKind::LParen {
+            self.next_token();
+            let (elements, _) = self.parse_parenthesized_list()?;
+            if self.peak_token.kind == TokenKind::Arrow {
+                return self.parse_arrow_function(elements, true);
+            }
+            let arguments = elements.into_iter().map(|(e, _)| e).collect();
+            return Some(Box::new(ast::CallExpression{ callee: Box::new(id), arguments, optional: false }));
+        }
+        return Some(Box::new(id));
+    }
+
+    /// Reports an identifier that is a reserved word in strict mode code.
+    fn check_identifier(&mut self, name: &str, span: Span) {
+        if self.strict && token::is_strict_mode_reserved_word(name) {
+            self.error(format!("'{}' is a reserved word in strict mode", name), span);
+        }
+    }
+
     fn parse_number(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
-        let literal = parser.current_token.literal;
-        if literal.starts_with("0x") || literal.starts_with("0X") {
-            match i64::from_str_radix(&literal[2..], 16) {
-                Ok(v) => return Some(Box::new(NumberLiteral{value: v as f64})),
-                Err(e) => {
-                    let msg = format!("fail to parse [{}]: {}", literal, e);
-                    parser.errors.push(msg);
+        // Malformed literals have been reported by the lexer already.
+        let value = number_value(parser.current_token.literal).unwrap_or(f64::NAN);
+        return Some(Box::new(NumberLiteral{ value }));
+    }
+
+    fn parse_boolean(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let value = parser.current_token.kind == TokenKind::True;
+        return Some(Box::new(ast::BooleanLiteral{ value }));
+    }
+
+    fn parse_null(_parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        return Some(Box::new(ast::NullLiteral{}));
+    }
+
+    /// Parses a parenthesized expression, or the parameters of an arrow function if
+    /// `=>` follows the `)`.
+    fn parse_grouped_expression(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let (mut elements, trailing_comma) = parser.parse_parenthesized_list()?;
+        if parser.peak_token.kind == TokenKind::Arrow {
+            return parser.parse_arrow_function(elements, false);
+        }
+        // `()`, `(a,)` and `(...a)` can only be arrow parameters.
+        if elements.is_empty() {
+            parser.no_prefix_error(TokenKind::RParen);
+            return None;
+        }
+        if trailing_comma || elements.iter().any(|(e, _)| e.is::<ast::SpreadElement>()) {
+            parser.peak_error(TokenKind::Arrow);
+            return None;
+        }
+        let expression = if elements.len() == 1 {
+            elements.pop().unwrap().0
+        } else {
+            let expressions = elements.into_iter().map(|(e, _)| e).collect();
+            Box::new(ast::SequenceExpression{ expressions })
+        };
+        return Some(Box::new(ast::GroupedExpression{ expression }));
+    }
+
+    /// Parses a comma separated list from the current `(` to its `)`. It covers both a
+    /// parenthesized expression and arrow function parameters, so it can hold spread
+    /// elements and end with a comma. Returns the elements with their spans, and whether
+    /// there is a trailing comma.
+    fn parse_parenthesized_list(&mut self) -> Option<(Vec<CoverElement>, bool)> {
+        let mut elements = Vec::new();
+        while self.peak_token.kind != TokenKind::RParen {
+            self.next_token();
+            let start = self.current_token.span.start;
+            let element: Box<dyn ast::Expression> = if self.current_token.kind == TokenKind::Ellipsis {
+                self.next_token();
+                let argument = self.parse_nested_expression(Precedence::Comma)?;
+                Box::new(ast::SpreadElement{ argument })
+            } else {
+                self.parse_nested_expression(Precedence::Comma)?
+            };
+            elements.push((element, Span::new(start, self.current_token.span.end)));
+            match self.peak_token.kind {
+                TokenKind::RParen => {}
+                TokenKind::Commas => self.next_token(),
+                _ => {
+                    self.peak_error(TokenKind::RParen);
+                    return None;
                 }
             }
         }
-        if literal.starts_with('0') {
-            match i64::from_str_radix(&literal, 8) {
-                Ok(v) => return Some(Box::new(NumberLiteral{value: v as f64})),
-                Err(e) => {
-                    let msg = format!("fail to parse [{}]: {}", literal, e);
-                    parser.errors.push(msg);
+        let trailing_comma = self.current_token.kind == TokenKind::Commas;
+        self.next_token();
+        return Some((elements, trailing_comma));
+    }
+
+    /// Parses an arrow function from its `=>`, which is the peak token, reinterpreting
+    /// the expressions before it as its parameters.
+    fn parse_arrow_function(&mut self, elements: Vec<CoverElement>, is_async: bool)
+        -> Option<Box<dyn ast::Expression>>
+    {
+        let mut params = Vec::new();
+        let mut spans = Vec::new();
+        let count = elements.len();
+        for (index, (element, span)) in elements.into_iter().enumerate() {
+            let (target, rest) = match element.downcast::<ast::SpreadElement>() {
+                Ok(spread) => (spread.argument, true),
+                Err(element) => (element, false),
+            };
+            if rest && index + 1 < count {
+                self.error("rest parameter must be last formal parameter", span);
+                return None;
+            }
+            // `(a = 1) => a` was parsed as an assignment.
+            let (target, default) = match target.downcast::<ast::AssignmentExpression>() {
+                Ok(assignment) if assignment.op == TokenKind::Assign && !rest => {
+                    (assignment.target, Some(assignment.value))
+                }
+                Ok(assignment) => (assignment as Box<dyn ast::Expression>, None),
+                Err(target) => (target, None),
+            };
+            match target.downcast::<ast::Identifier>() {
+                Ok(id) => params.push(ast::Parameter{ id: *id, default, rest }),
+                Err(_) => {
+                    self.error("malformed arrow function parameter list", span);
+                    return None;
+                }
+            }
+            spans.push(span);
+        }
+        if self.peak_token.newline_before {
+            self.error("line terminator not permitted before arrow", self.peak_token.span);
+        }
+        self.next_token();
+
+        let strict = self.strict;
+        self.scopes.push(Scope{ function: true, ..Default::default() });
+        for (param, span) in params.iter().zip(&spans) {
+            self.declare(TokenKind::Var, &param.id.name, *span);
+        }
+        self.next_token();
+        let in_async = std::mem::replace(&mut self.in_async, is_async);
+        let body = if self.current_token.kind == TokenKind::LBrace {
+            let no_in = std::mem::replace(&mut self.no_in, false);
+            let statements = self.parse_statement_list();
+            self.no_in = no_in;
+            statements.map(|statements| {
+                ast::ArrowBody::Block(ast::BlockStatement{ statements, lexical_names: Vec::new() })
+            })
+        } else {
+            self.parse_expression(Precedence::Comma).map(ast::ArrowBody::Expression)
+        };
+        self.in_async = in_async;
+        let scope = self.scopes.pop().unwrap();
+        let mut body = body?;
+        if let ast::ArrowBody::Block(block) = &mut body {
+            block.lexical_names = scope.lexical;
+        }
+        self.check_parameters(&params, &spans, true);
+        self.strict = strict;
+        return Some(Box::new(ast::ArrowFunction{ params, body, is_async }));
+    }
+
+    fn parse_function_expression(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let function = parser.parse_function(false, false)?;
+        return Some(Box::new(function));
+    }
+
+    fn parse_call_expression(parser: &mut Parser, callee: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        let arguments = parser.parse_arguments()?;
+        return Some(Box::new(ast::CallExpression{ callee, arguments, optional: false }));
+    }
+
+    fn parse_member_expression(parser: &mut Parser, object: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        return parser.parse_member_property(object, false);
+    }
+
+    /// Parses what follows `?.`: a property name, a computed property, or arguments.
+    fn parse_optional_chain(parser: &mut Parser, object: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        match parser.peak_token.kind {
+            TokenKind::LParen => {
+                parser.next_token();
+                let arguments = parser.parse_arguments()?;
+                Some(Box::new(ast::CallExpression{ callee: object, arguments, optional: true }))
+            }
+            TokenKind::LBracket => {
+                parser.next_token();
+                parser.parse_member_property(object, true)
+            }
+            _ => parser.parse_member_property(object, true),
+        }
+    }
+
+    /// Parses the property of a member expression after the current `.`, `?.` or `[`.
+    fn parse_member_property(&mut self, object: Box<dyn ast::Expression>, optional: bool) -> Option<Box<dyn ast::Expression>> {
+        if self.current_token.kind == TokenKind::LBracket {
+            self.next_token();
+            let property = self.parse_nested_expression(Precedence::Lowest)?;
+            if !self.expect_peak(TokenKind::RBracket) {
+                return None;
+            }
+            return Some(Box::new(ast::MemberExpression{ object, property, computed: true, optional }));
+        }
+        // Reserved words are fine as property names, as in `a.if`.
+        if !self.peak_token.is_identifier_name() {
+            self.peak_error(TokenKind::Identifier);
+            return None;
+        }
+        self.next_token();
+        let property = Box::new(ast::Identifier{ name: identifier_name(self.current_token.literal) });
+        return Some(Box::new(ast::MemberExpression{ object, property, computed: false, optional }));
+    }
+
+    fn parse_array_literal(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let mut elements = Vec::new();
+        let mut spans = Vec::new();
+        while parser.peak_token.kind != TokenKind::RBracket {
+            // A comma with nothing before it leaves a hole.
+            if parser.peak_token.kind == TokenKind::Commas {
+                parser.next_token();
+                elements.push(None);
+                spans.push(Span::new(parser.current_token.span.start, parser.current_token.span.start));
+                continue;
+            }
+            parser.next_token();
+            let start = parser.current_token.span.start;
+            let element: Box<dyn ast::Expression> = if parser.current_token.kind == TokenKind::Ellipsis {
+                parser.next_token();
+                let argument = parser.parse_nested_expression(Precedence::Comma)?;
+                Box::new(ast::SpreadElement{ argument })
+            } else {
+                parser.parse_nested_expression(Precedence::Comma)?
+            };
+            elements.push(Some(element));
+            spans.push(Span::new(start, parser.current_token.span.end));
+            match parser.peak_token.kind {
+                TokenKind::RBracket => {}
+                TokenKind::Commas => parser.next_token(),
+                _ => {
+                    parser.peak_error(TokenKind::RBracket);
+                    return None;
                 }
             }
         }
-        match literal.parse::<f64>() {
-            Ok(v) => return Some(Box::new(NumberLiteral{value: v})),
-            Err(e) => {
-                let msg = format!("fail to parse [{}]: {}", literal, e);
-                parser.errors.push(msg);
+        parser.next_token();
+        return Some(Box::new(ast::ArrayLiteral{ elements, spans }));
+    }
+
+    fn parse_object_literal(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let mut properties = Vec::new();
+        let mut spans = Vec::new();
+        while parser.peak_token.kind != TokenKind::RBrace {
+            parser.next_token();
+            let start = parser.current_token.span.start;
+            let property = if parser.current_token.kind == TokenKind::Ellipsis {
+                parser.next_token();
+                let argument = parser.parse_nested_expression(Precedence::Comma)?;
+                ast::ObjectProperty::Spread(ast::SpreadElement{ argument })
+            } else {
+                ast::ObjectProperty::Property(parser.parse_property()?)
+            };
+            properties.push(property);
+            spans.push(Span::new(start, parser.current_token.span.end));
+            match parser.peak_token.kind {
+                TokenKind::RBrace => {}
+                TokenKind::Commas => parser.next_token(),
+                _ => {
+                    parser.peak_error(TokenKind::RBrace);
+                    return None;
+                }
             }
         }
-        return None;
+        parser.next_token();
+        return Some(Box::new(ast::ObjectLiteral{ properties, spans }));
+    }
+
+    /// Parses a property of an object literal starting at the current token.
+    fn parse_property(&mut self) -> Option<ast::Property> {
+        // `get` and `set` start an accessor only if a property name follows them.
+        let accessor = match self.current_token.kind {
+            TokenKind::Get => ast::PropertyKind::Get,
+            TokenKind::Set => ast::PropertyKind::Set,
+            _ => ast::PropertyKind::Init,
+        };
+        let kind = match self.peak_token.kind {
+            TokenKind::LParen | TokenKind::Colon | TokenKind::Commas | TokenKind::RBrace | TokenKind::Assign => {
+                ast::PropertyKind::Init
+            }
+            _ => accessor,
+        };
+        // Likewise `async` starts an async method only if a name follows it on the same line.
+        let is_async = self.current_token.kind == TokenKind::Async && !self.peak_token.newline_before
+            && (self.peak_token.is_identifier_name() || matches!(self.peak_token.kind,
+                TokenKind::String | TokenKind::Number | TokenKind::BigInt | TokenKind::LBracket));
+        if kind != ast::PropertyKind::Init || is_async {
+            self.next_token();
+        }
+        let shorthand = kind == ast::PropertyKind::Init && !is_async && self.current_token.is_identifier();
+        let span = self.current_token.span;
+        let (key, computed) = self.parse_property_key()?;
+
+        if kind != ast::PropertyKind::Init || is_async || self.peak_token.kind == TokenKind::LParen {
+            let function = self.parse_function_rest(None, is_async)?;
+            let count = function.params.len();
+            if kind == ast::PropertyKind::Get && count != 0 {
+                self.error("getter must not have any formal parameters", span);
+            }
+            if kind == ast::PropertyKind::Set && (count != 1 || function.params[0].rest) {
+                self.error("setter must have exactly one formal parameter", span);
+            }
+            let method = kind == ast::PropertyKind::Init;
+            return Some(ast::Property{ key, computed, value: Box::new(function), kind, method, shorthand: false });
+        }
+        if self.peak_token.kind == TokenKind::Colon {
+            self.next_token();
+            self.next_token();
+            let value = self.parse_nested_expression(Precedence::Comma)?;
+            return Some(ast::Property{ key, computed, value, kind, method: false, shorthand: false });
+        }
+        if !shorthand {
+            self.peak_error(TokenKind::Colon);
+            return None;
+        }
+        let name = key.downcast_ref::<ast::Identifier>().unwrap().name.clone();
+        self.check_identifier(&name, span);
+        let mut value: Box<dyn ast::Expression> = Box::new(ast::Identifier{ name });
+        // `{a = 1}` is only valid as a pattern, where it is short for `{a: a = 1}`.
+        if self.peak_token.kind == TokenKind::Assign {
+            self.next_token();
+            self.next_token();
+            let default = self.parse_nested_expression(Precedence::Comma)?;
+            value = Box::new(ast::AssignmentExpression{ op: TokenKind::Assign, target: value, value: default });
+            self.cover_initializers.push(Span::new(span.start, self.current_token.span.end));
+        }
+        return Some(ast::Property{ key, computed, value, kind, method: false, shorthand: true });
+    }
+
+    /// Parses a property name at the current token, which may be a computed `[key]`.
+    fn parse_property_key(&mut self) -> Option<(Box<dyn ast::Expression>, bool)> {
+        let key: Box<dyn ast::Expression> = match self.current_token.kind {
+            TokenKind::String => Self::parse_string(self)?,
+            TokenKind::Number => Self::parse_number(self)?,
+            TokenKind::BigInt => Self::parse_bigint(self)?,
+            TokenKind::LBracket => {
+                self.next_token();
+                let key = self.parse_nested_expression(Precedence::Comma)?;
+                if !self.expect_peak(TokenKind::RBracket) {
+                    return None;
+                }
+                return Some((key, true));
+            }
+            _ if self.current_token.is_identifier_name() => {
+                Box::new(ast::Identifier{ name: identifier_name(self.current_token.literal) })
+            }
+            kind => {
+                let msg = format!("expected property name, got {:?} instead", kind);
+                self.error(msg, self.current_token.span);
+                return None;
+            }
+        };
+        return Some((key, false));
+    }
+
+    /// Parses an argument list from the current `(` to its `)`.
+    fn parse_arguments(&mut self) -> Option<Vec<Box<dyn ast::Expression>>> {
+        let mut arguments = Vec::new();
+        while self.peak_token.kind != TokenKind::RParen {
+            self.next_token();
+            let argument: Box<dyn ast::Expression> = if self.current_token.kind == TokenKind::Ellipsis {
+                self.next_token();
+                let argument = self.parse_nested_expression(Precedence::Comma)?;
+                Box::new(ast::SpreadElement{ argument })
+            } else {
+                self.parse_nested_expression(Precedence::Comma)?
+            };
+            arguments.push(argument);
+            // A trailing comma is allowed.
+            if self.peak_token.kind != TokenKind::RParen && !self.expect_peak(TokenKind::Commas) {
+                return None;
+            }
+        }
+        self.next_token();
+        return Some(arguments);
+    }
+
+    fn parse_bigint(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let literal = parser.current_token.literal;
+        let digits = literal.strip_suffix('n').unwrap_or(literal).replace('_', "");
+        return Some(Box::new(ast::BigIntLiteral{ digits }));
+    }
+
+    fn parse_string(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let literal = parser.current_token.literal;
+        let body = &literal[1..];
+        let body = body.strip_suffix(&literal[..1]).unwrap_or(body);
+        // Bad escapes have been reported by the lexer already.
+        let value = escape::cook(body, false).unwrap_or_else(|_| body.to_string());
+        return Some(Box::new(ast::StringLiteral{ value }));
+    }
+
+    fn parse_regexp(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        parser.rescan_regexp();
+        let literal = parser.current_token.literal;
+        let end = literal.rfind('/').filter(|end| *end > 0).unwrap_or(literal.len());
+        let regexp = ast::RegExpLiteral{
+            pattern: literal[1..end].to_string(),
+            flags: literal[end..].trim_start_matches('/').to_string(),
+        };
+        return Some(Box::new(regexp));
+    }
+
+    fn parse_template_literal(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let template = parser.parse_template(false)?;
+        return Some(Box::new(template));
+    }
+
+    fn parse_tagged_template(parser: &mut Parser, tag: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        if is_optional_chain(&*tag) {
+            parser.error("tagged template cannot be used in optional chain", parser.current_token.span);
+        }
+        let quasi = parser.parse_template(true)?;
+        return Some(Box::new(ast::TaggedTemplateExpression{ tag, quasi }));
+    }
+
+    /// Parses a template starting at the current `Template` or `TemplateHead` token,
+    /// leaving the parser on its last token.
+    fn parse_template(&mut self, tagged: bool) -> Option<ast::TemplateLiteral> {
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+        loop {
+            let literal = self.current_token.literal;
+            let body = &literal[1..];
+            let body = body.strip_suffix('`')
+                .or_else(|| body.strip_suffix("${"))
+                .unwrap_or(body);
+            let cooked = match escape::cook(body, true) {
+                Ok(cooked) => Some(cooked),
+                Err(e) => {
+                    if !tagged {
+                        self.error(e.to_string(), self.current_token.span);
+                    }
+                    None
+                }
+            };
+            let raw = body.replace("\r\n", "\n").replace('\r', "\n");
+            quasis.push(ast::TemplateElement{ cooked, raw });
+
+            match self.current_token.kind {
+                TokenKind::TemplateHead | TokenKind::TemplateMiddle => {}
+                _ => break,
+            }
+            self.next_token();
+            expressions.push(self.parse_nested_expression(Precedence::Lowest)?);
+            match self.peak_token.kind {
+                TokenKind::TemplateMiddle | TokenKind::TemplateTail => self.next_token(),
+                _ => {
+                    self.peak_error(TokenKind::TemplateTail);
+                    return None;
+                }
+            }
+        }
+        return Some(ast::TemplateLiteral{ quasis, expressions });
     }
 
     fn parse_prefix_expression(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
         let op = parser.current_token.kind;
         parser.next_token();
-        if let Some(e) = parser.parse_expression(Precedence::Prefix) {
-            Some(Box::new(PrefixExpression{ op, expression: e}))
-        } else {
-            None
+        let start = parser.current_token.span.start;
+        let expression = parser.parse_expression(Precedence::Prefix)?;
+        if op == TokenKind::Delete && parser.strict && is_identifier_reference(&*expression) {
+            let span = Span::new(start, parser.current_token.span.end);
+            parser.error("delete of an unqualified identifier in strict mode", span);
         }
+        return Some(Box::new(PrefixExpression{ op, expression }));
     }
 
     fn parse_infix_expression(parser: &mut Parser, lhs: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        let lhs_span = Span::new(parser.lhs_start, parser.current_token.span.end);
         // For reasons why we call it here, see [parse_expression]
         parser.next_token();
 
         let op = parser.current_token.kind;
-        let precedence = parser.current_precedence();
+        let mut precedence = parser.current_precedence();
+        if op == TokenKind::Exponent {
+            // `-a ** b` could mean `(-a) ** b` or `-(a ** b)`, so it needs parentheses.
+            if lhs.is::<PrefixExpression>() {
+                parser.error("unary operator used immediately before exponentiation expression", lhs_span);
+            }
+            // Right-associative: let the right-hand side take the next `**` as well.
+            precedence = Precedence::Product;
+        }
         parser.next_token();
         if let Some(rhs) = parser.parse_expression(precedence) {
             return Some(Box::new(
@@ -232,8 +1451,148 @@ impl<'a> Parser<'a> {
         return None;
     }
 
+    fn parse_logical_expression(parser: &mut Parser, lhs: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        let op = parser.current_token.kind;
+        let span = parser.current_token.span;
+        let precedence = parser.current_precedence();
+        parser.next_token();
+        let rhs = parser.parse_expression(precedence)?;
+        // `a ?? b || c` has no obvious meaning, so it needs parentheses either way.
+        let nullish = op == TokenKind::Nullish;
+        let mixed = [&lhs, &rhs].into_iter().any(|operand| {
+            operand.downcast_ref::<ast::LogicalExpression>()
+                .is_some_and(|operand| (operand.op == TokenKind::Nullish) != nullish)
+        });
+        if mixed {
+            parser.error("'??' cannot be mixed with '&&' or '||' without parentheses", span);
+        }
+        return Some(Box::new(ast::LogicalExpression{ op, lhs, rhs }));
+    }
+
+    fn parse_sequence_expression(parser: &mut Parser, lhs: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        parser.next_token();
+        let rhs = parser.parse_expression(Precedence::Comma)?;
+        // `a, b, c` is one sequence rather than a nested one.
+        let mut expressions = match lhs.downcast::<ast::SequenceExpression>() {
+            Ok(sequence) => sequence.expressions,
+            Err(lhs) => vec![lhs],
+        };
+        expressions.push(rhs);
+        return Some(Box::new(ast::SequenceExpression{ expressions }));
+    }
+
+    fn parse_conditional_expression(parser: &mut Parser, test: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        parser.next_token();
+        // `in` is an operator here even in the head of a `for` loop.
+        let consequent = parser.parse_nested_expression(Precedence::Comma)?;
+        if !parser.expect_peak(TokenKind::Colon) {
+            return None;
+        }
+        parser.next_token();
+        // Right-associative: `a ? b : c ? d : e` nests in the alternate.
+        let alternate = parser.parse_expression(Precedence::Comma)?;
+        return Some(Box::new(ast::ConditionalExpression{ test, consequent, alternate }));
+    }
+
+    fn parse_assignment_expression(parser: &mut Parser, target: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        let span = Span::new(parser.lhs_start, parser.current_token.span.end);
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        let op = parser.current_token.kind;
+        // Only a plain `=` can destructure.
+        let invalid = if op == TokenKind::Assign {
+            invalid_target_span(&*target, span)
+        } else if !is_simple_assignment_target(&*target) {
+            Some(span)
+        } else {
+            None
+        };
+        if let Some(span) = invalid {
+            parser.error("invalid left-hand side in assignment", span);
+        }
+        if op == TokenKind::Assign {
+            parser.settle_cover_initializers(&*target);
+        }
+        parser.check_strict_target(&*target, span);
+        parser.next_token();
+        // Right-associative: `a = b = c` assigns `b = c` to `a`.
+        let value = parser.parse_expression(Precedence::Comma)?;
+        return Some(Box::new(ast::AssignmentExpression{ op, target, value }));
+    }
+
+    /// Drops the shorthand initializers in `target` from those still to be reported,
+    /// now that it has been reinterpreted as a pattern.
+    fn settle_cover_initializers(&mut self, target: &(dyn ast::Expression + 'static)) {
+        let mut spans = Vec::new();
+        pattern_initializer_spans(target, &mut spans);
+        self.cover_initializers.retain(|span| !spans.contains(span));
+    }
+
+    fn parse_prefix_update(parser: &mut Parser) -> Option<Box<dyn ast::Expression>> {
+        let op = parser.current_token.kind;
+        parser.next_token();
+        let start = parser.current_token.span.start;
+        let argument = parser.parse_expression(Precedence::Prefix)?;
+        let span = Span::new(start, parser.current_token.span.end);
+        if !is_simple_assignment_target(&*argument) {
+            parser.error("invalid left-hand side expression in prefix operation", span);
+        }
+        parser.check_strict_target(&*argument, span);
+        return Some(Box::new(ast::UpdateExpression{ op, prefix: true, argument }));
+    }
+
+    fn parse_postfix_update(parser: &mut Parser, argument: Box<dyn ast::Expression>) -> Option<Box<dyn ast::Expression>> {
+        let span = Span::new(parser.lhs_start, parser.current_token.span.end);
+        // For reasons why we call it here, see [parse_expression]
+        parser.next_token();
+
+        if !is_simple_assignment_target(&*argument) {
+            parser.error("invalid left-hand side expression in postfix operation", span);
+        }
+        parser.check_strict_target(&*argument, span);
+        let op = parser.current_token.kind;
+        return Some(Box::new(ast::UpdateExpression{ op, prefix: false, argument }));
+    }
+
+    /// Strict mode code can't assign to `eval` or `arguments`.
+    fn check_strict_target(&mut self, target: &(dyn ast::Expression + 'static), span: Span) {
+        let target = match target.downcast_ref::<ast::GroupedExpression>() {
+            Some(grouped) => &*grouped.expression,
+            None => target,
+        };
+        if let Some(id) = target.downcast_ref::<ast::Identifier>() {
+            if self.strict && (id.name == "eval" || id.name == "arguments") {
+                self.error("unexpected eval or arguments in strict mode", span);
+            }
+        }
+    }
+
     fn next_token(&mut self) {
+        // What the lexer reported so far is about the peak token, which is about to
+        // become current. Leave the errors for the next one in the lexer until then,
+        // in case it has to be re-scanned.
+        self.errors.append(&mut self.lexer.take_errors());
         self.current_token.clone_from(&self.peak_token);
+        self.current_checkpoint = std::mem::replace(&mut self.peak_checkpoint, self.lexer.checkpoint());
+        self.peak_token = self.lexer.next_token();
+    }
+
+    /// Re-scans the current `/` or `/=` token as the start of a regular expression.
+    fn rescan_regexp(&mut self) {
+        self.lexer.reset(self.current_checkpoint.clone());
+        self.current_token = self.lexer.next_regexp_token();
+        self.errors.append(&mut self.lexer.take_errors());
+        self.peak_checkpoint = self.lexer.checkpoint();
         self.peak_token = self.lexer.next_token();
     }
 
@@ -247,18 +1606,36 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Like `expect_peak(TokenKind::Identifier)`, but also accepts contextual keywords.
+    fn expect_peak_identifier(&mut self) -> bool {
+        if self.peak_token.is_identifier() {
+            self.next_token();
+            true
+        } else {
+            self.peak_error(TokenKind::Identifier);
+            false
+        }
+    }
+
     fn peak_error(&mut self, expect: TokenKind) {
         let msg = format!("expected next token to be {:?}, got {:?} instead",
                 expect, self.peak_token.kind);
-        self.errors.push(msg);
+        self.error(msg, self.peak_token.span);
     }
 
     fn no_prefix_error(&mut self, kind: TokenKind) {
         let msg = format!("no prefix parse function for {:?} found", kind);
-        self.errors.push(msg);
+        self.error(msg, self.current_token.span);
+    }
+
+    fn error<S: Into<String>>(&mut self, message: S, span: Span) {
+        self.errors.push(SyntaxError::new(message, span));
     }
 
     fn peak_precedence(&self) -> Precedence {
+        if self.no_in && self.peak_token.kind == TokenKind::In {
+            return Precedence::Lowest;
+        }
         if let Some(p) = PRECEDENCES.get(&self.peak_token.kind) {
             return *p
         }
@@ -273,3 +1650,149 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// The name an identifier token stands for, with escapes such as `\u0061` decoded.
+fn identifier_name(literal: &str) -> String {
+    if literal.contains('\\') {
+        escape::cook_identifier(literal)
+    } else {
+        literal.to_string()
+    }
+}
+
+/// Whether `expression` is an identifier or a property, possibly in parentheses,
+/// which is all an assignment can store to apart from destructuring patterns.
+fn is_simple_assignment_target(expression: &(dyn ast::Expression + 'static)) -> bool {
+    if let Some(grouped) = expression.downcast_ref::<ast::GroupedExpression>() {
+        return is_simple_assignment_target(&*grouped.expression);
+    }
+    if let Some(member) = expression.downcast_ref::<ast::MemberExpression>() {
+        return !is_optional_chain(expression) && !member.optional;
+    }
+    return expression.is::<ast::Identifier>();
+}
+
+/// Whether `expression` is a plain identifier, possibly in parentheses.
+fn is_identifier_reference(expression: &(dyn ast::Expression + 'static)) -> bool {
+    if let Some(grouped) = expression.downcast_ref::<ast::GroupedExpression>() {
+        return is_identifier_reference(&*grouped.expression);
+    }
+    return expression.is::<ast::Identifier>();
+}
+
+/// Finds what can't be assigned to with `=` in `target`, whose span is `span`: the
+/// target itself unless it is a simple one, or an object or array literal that reads
+/// as a destructuring pattern. Then it is the span of the first bad element.
+fn invalid_target_span(target: &(dyn ast::Expression + 'static), span: Span) -> Option<Span> {
+    if is_simple_assignment_target(target) {
+        return None;
+    }
+    if let Some(array) = target.downcast_ref::<ast::ArrayLiteral>() {
+        let count = array.elements.len();
+        for (index, (element, span)) in array.elements.iter().zip(&array.spans).enumerate() {
+            let Some(element) = element else { continue };
+            let invalid = match element.downcast_ref::<ast::SpreadElement>() {
+                // A rest element comes last and has no default.
+                Some(_) if index + 1 < count => Some(*span),
+                Some(spread) => invalid_target_span(&*spread.argument, *span),
+                None => invalid_pattern_element_span(&**element, *span),
+            };
+            if invalid.is_some() {
+                return invalid;
+            }
+        }
+        return None;
+    }
+    if let Some(object) = target.downcast_ref::<ast::ObjectLiteral>() {
+        let count = object.properties.len();
+        for (index, (property, span)) in object.properties.iter().zip(&object.spans).enumerate() {
+            let invalid = match property {
+                // Only an identifier or a property can take the rest of an object.
+                ast::ObjectProperty::Spread(spread) => {
+                    let valid = index + 1 == count && is_simple_assignment_target(&*spread.argument);
+                    if valid { None } else { Some(*span) }
+                }
+                ast::ObjectProperty::Property(p) if p.method || p.kind != ast::PropertyKind::Init => Some(*span),
+                ast::ObjectProperty::Property(p) => invalid_pattern_element_span(&*p.value, *span),
+            };
+            if invalid.is_some() {
+                return invalid;
+            }
+        }
+        return None;
+    }
+    return Some(span);
+}
+
+/// Collects the spans of the shorthand properties with an initializer in the pattern
+/// `target`, following the same elements as [invalid_target_span].
+fn pattern_initializer_spans(target: &(dyn ast::Expression + 'static), spans: &mut Vec<Span>) {
+    // Skip a default, which is an expression rather than part of the pattern.
+    let target = match target.downcast_ref::<ast::AssignmentExpression>() {
+        Some(assignment) if assignment.op == TokenKind::Assign => &*assignment.target,
+        _ => target,
+    };
+    if let Some(array) = target.downcast_ref::<ast::ArrayLiteral>() {
+        for element in array.elements.iter().flatten() {
+            match element.downcast_ref::<ast::SpreadElement>() {
+                Some(spread) => pattern_initializer_spans(&*spread.argument, spans),
+                None => pattern_initializer_spans(&**element, spans),
+            }
+        }
+    }
+    if let Some(object) = target.downcast_ref::<ast::ObjectLiteral>() {
+        for (property, span) in object.properties.iter().zip(&object.spans) {
+            match property {
+                ast::ObjectProperty::Property(p) if p.shorthand && p.value.is::<ast::AssignmentExpression>() => {
+                    spans.push(*span);
+                }
+                ast::ObjectProperty::Property(p) => pattern_initializer_spans(&*p.value, spans),
+                ast::ObjectProperty::Spread(_) => {}
+            }
+        }
+    }
+}
+
+/// Like [invalid_target_span], for an element of a pattern, which may have a default
+/// as in `[a = 1] = b`.
+fn invalid_pattern_element_span(element: &(dyn ast::Expression + 'static), span: Span) -> Option<Span> {
+    if let Some(assignment) = element.downcast_ref::<ast::AssignmentExpression>() {
+        if assignment.op == TokenKind::Assign {
+            return invalid_target_span(&*assignment.target, span);
+        }
+    }
+    return invalid_target_span(element, span);
+}
+
+/// Whether `expression` is part of an optional chain like `a?.b.c`, which
+/// parentheses end.
+fn is_optional_chain(expression: &(dyn ast::Expression + 'static)) -> bool {
+    if let Some(member) = expression.downcast_ref::<ast::MemberExpression>() {
+        return member.optional || is_optional_chain(&*member.object);
+    }
+    if let Some(call) = expression.downcast_ref::<ast::CallExpression>() {
+        return call.optional || is_optional_chain(&*call.callee);
+    }
+    return false;
+}
+
+/// Whether a directive is exactly `"use strict"` or `'use strict'`, without escapes.
+fn is_use_strict(literal: &str) -> bool {
+    literal.len() == 12 && literal.get(1..11) == Some("use strict")
+}
+
+/// The value of a numeric literal, or `None` if it is malformed.
+fn number_value(literal: &str) -> Option<f64> {
+    let literal = literal.replace('_', "");
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x" | "0X") => (&literal[2..], 16),
+        Some("0o" | "0O") => (&literal[2..], 8),
+        Some("0b" | "0B") => (&literal[2..], 2),
+        _ if literal.len() > 1 && literal.starts_with('0')
+                && literal.bytes().all(|b| (b'0'..=b'7').contains(&b)) => (&literal[1..], 8),
+        _ => return literal.parse().ok(),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(0.0, |value, c| Some(value * radix as f64 + c.to_digit(radix)? as f64))
+}
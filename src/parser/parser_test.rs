@@ -7,6 +7,7 @@ use crate::lexer;
 use crate::parser::*;
 
 use self::ast::Program;
+use crate::lexer::span::Span;
 
 #[test]
 fn test_let_statements() {
@@ -22,9 +23,9 @@ let foobar = 838383;
     check_parser_errors(&mut parser);
 
     assert_eq!(program.statements.len(), 3);
-    test_statement(&program.statements[0], "let x = \"no-op\";");
-    test_statement(&program.statements[1], "let y = \"no-op\";");
-    test_statement(&program.statements[2], "let foobar = \"no-op\";");
+    test_statement(&*program.statements[0], "let x = 5;");
+    test_statement(&*program.statements[1], "let y = 10;");
+    test_statement(&*program.statements[2], "let foobar = 838383;");
 }
 
 #[test]
@@ -54,7 +55,7 @@ fn check_parser_errors(parser: &mut Parser) {
     panic!();
 }
 
-fn test_statement(s: &Box<dyn ast::Statement>, string: &str) {
+fn test_statement(s: &dyn ast::Statement, string: &str) {
     assert_eq!(s.to_string(), string);
 }
 
@@ -63,17 +64,14 @@ fn test_return_statement() {
     let input = r"
 return 5;
 return 10;
-return 993 322;
+return 993 * 322;
 ";
     let lexer = lexer::Lexer::new(input);
     let mut parser = Parser::new(lexer);
     let program = parser.parse_program();
     check_parser_errors(&mut parser);
 
-    assert_eq!(program.statements.len(), 3);
-    for statement in &program.statements {
-        assert_eq!(statement.to_string(), "return \"no-op\";");
-    }
+    test_statements(&program, &["return 5;", "return 10;", "return (993 * 322);"]);
 }
 
 #[test]
@@ -96,6 +94,7 @@ fn test_number_literal() {
 5; 012; 034; 0; 042;
 0x12; 0xff; 0Xfd; 0x42;
 12.2; 2.4e8;
+09; 08.5; .5; 0b101; 0o17; 0O17; 1_000; 0xffff_ffff_ffff_ffff;
 ";
     let lexer = lexer::Lexer::new(input);
     let mut parser = Parser::new(lexer);
@@ -116,6 +115,15 @@ fn test_number_literal() {
 
         "12.2;",
         "240000000;",
+
+        "9;",
+        "8.5;",
+        "0.5;",
+        "5;",
+        "15;",
+        "15;",
+        "1000;",
+        "18446744073709552000;",
     ];
 
     test_statements(&program, &expects);
@@ -133,6 +141,17 @@ fn test_prefix_expression() {
     let input = r"
 -5;
 !5;
++a;
+~a;
+typeof a;
+void 0;
+delete a.b;
+delete a;
+-~!a;
+typeof typeof a;
+typeof a === 'string';
+void a, b;
++a.b();
 ";
     let lexer = lexer::Lexer::new(input);
     let mut parser = Parser::new(lexer);
@@ -142,6 +161,18 @@ fn test_prefix_expression() {
     let expects = [
         "(-5);",
         "(!5);",
+        "(+a);",
+        "(~a);",
+        "(typeof a);",
+        "(void 0);",
+        "(delete a.b);",
+        // Only strict mode code can't delete an identifier.
+        "(delete a);",
+        "(-(~(!a)));",
+        "(typeof (typeof a));",
+        "((typeof a) === \"string\");",
+        "(void a), b;",
+        "(+a.b());",
     ];
 
     test_statements(&program, &expects);
@@ -215,4 +246,1390 @@ a + b * c + d / e - f;
 
     test_statements(&program, &expects);
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_string_literal() {
+    let input = r#"
+"hello";
+'x';
+'say "hi"';
+"tab\there\u0021";
+"#;
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        r#""hello";"#,
+        r#""x";"#,
+        r#""say \"hi\"";"#,
+        r#""tab\there!";"#,
+    ];
+
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_string_literal_errors() {
+    let input = "\"\\x4\";\n'unterminated\n";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("invalid hexadecimal escape sequence", Span::new(1, 3)),
+        ("unterminated string literal", Span::new(7, 20)),
+    ]);
+}
+
+#[test]
+fn test_template_literal() {
+    let input = r"
+`plain`;
+`a ${b} c`;
+`${1 + 2}${`nested ${x}`}`;
+tag`x${a}y`;
+a`x``y`;
+raw`\unicode`;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "`plain`;",
+        "`a ${b} c`;",
+        "`${(1 + 2)}${`nested ${x}`}`;",
+        "tag`x${a}y`;",
+        "a`x``y`;",
+        r"raw`\unicode`;",
+    ];
+
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_template_literal_errors() {
+    let input = r"
+`\unicode`;
+`${a b}`;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].message, "invalid Unicode escape sequence");
+    assert_eq!(parser.errors()[1].message, "expected next token to be TemplateTail, got Identifier instead");
+}
+
+#[test]
+fn test_comments_are_ignored() {
+    let input = r"
+// leading
+1 + /* inline */ 2; // trailing
+/* multi
+   line */ a;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["(1 + 2);", "a;"]);
+}
+
+#[test]
+fn test_unicode_identifier_expression() {
+    let input = "café; \\u0061bc; \\u{1d4b3};";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["café;", "abc;", "\u{1d4b3};"]);
+}
+
+#[test]
+fn test_bigint_literal() {
+    let input = "12n; 0x1F_FFn; 0n;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["12n;", "0x1FFFn;", "0n;"]);
+}
+
+#[test]
+fn test_malformed_number_reported_once() {
+    let input = "12abc; 0x;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    let messages: Vec<_> = parser.errors().iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, [
+        "identifier starts immediately after numeric literal",
+        "expected digits after 0x",
+    ]);
+}
+
+#[test]
+fn test_regexp_literal() {
+    let input = r#"
+/ab+c/gi;
+a / b / c;
+-/[/]/;
+`${ /}/ }`;
+/"/ + '"';
+"#;
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "/ab+c/gi;",
+        "((a / b) / c);",
+        "(-/[/]/);",
+        "`${/}/}`;",
+        r#"(/"/ + "\"");"#,
+    ];
+
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_regexp_literal_errors() {
+    let input = "/abc\n;/a/gg;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("unterminated regular expression literal", Span::new(0, 4)),
+        ("invalid regular expression flags", Span::new(9, 11)),
+    ]);
+}
+
+#[test]
+fn test_extended_binary_operators() {
+    let input = r"
+a % b * c;
+a <= b == c >= d;
+a << b + c;
+a >>> b < c;
+a | b ^ c & d;
+a & b == c;
+a || b && c;
+a ?? b | c;
+2 ** 3 ** 2;
+2 ** 3 * 4;
+a * b ** c;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "((a % b) * c);",
+        "((a <= b) == (c >= d));",
+        "(a << (b + c));",
+        "((a >>> b) < c);",
+        "(a | (b ^ (c & d)));",
+        "(a & (b == c));",
+        "(a || (b && c));",
+        "(a ?? (b | c));",
+        "(2 ** (3 ** 2));",
+        "((2 ** 3) * 4);",
+        "(a * (b ** c));",
+    ];
+
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_regexp_after_slash_assign() {
+    let input = "/=a/g;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["/=a/g;"]);
+}
+
+#[test]
+fn test_automatic_semicolon_insertion() {
+    let input = "a\nb\nreturn\nx\nreturn 1\nlet y = 2\n;;c /*\n*/ d\ne";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "a;",
+        "b;",
+        "return;",
+        "x;",
+        "return 1;",
+        "let y = 2;",
+        ";",
+        "c;",
+        "d;",
+        "e;",
+    ];
+
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_throw_statement() {
+    let input = "throw x\nthrow new_error(1) + 2;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["throw x;", "throw (new_error(1) + 2);"]);
+
+    // `throw` is a restricted production, but no `;` can be inserted after it.
+    let lexer = lexer::Lexer::new("throw\nx;");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    let error = &parser.errors()[0];
+    assert_eq!((error.message.as_str(), error.span), ("illegal newline after throw", Span::new(0, 5)));
+}
+
+#[test]
+fn test_let_without_semicolon_at_end() {
+    let input = "let x = 5";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["let x = 5;"]);
+}
+
+#[test]
+fn test_missing_semicolon() {
+    let input = "a b; 1 + 2 3";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("expected next token to be Semicolon, got Identifier instead", Span::new(2, 3)),
+        ("expected next token to be Semicolon, got Number instead", Span::new(11, 12)),
+    ]);
+    test_statements(&program, &["a;", "b;", "(1 + 2);", "3;"]);
+}
+
+#[test]
+fn test_contextual_keywords_as_identifiers() {
+    let input = "let of = async; get + set; let\n- static; yield; let as = from";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "let of = async;",
+        "(get + set);",
+        "(let - static);",
+        "yield;",
+        "let as = from;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_strict_mode_reserved_words() {
+    let input = "implements + package; let static = 1";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let input = "'use strict'\nimplements + package; let static = 1; let let = 2; l\\u0065t;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("'implements' is a reserved word in strict mode", Span::new(13, 23)),
+        ("'package' is a reserved word in strict mode", Span::new(26, 33)),
+        ("'static' is a reserved word in strict mode", Span::new(39, 45)),
+        ("'let' cannot be a lexically bound name", Span::new(55, 58)),
+        ("'let' is a reserved word in strict mode", Span::new(55, 58)),
+        ("'let' is a reserved word in strict mode", Span::new(64, 72)),
+    ]);
+}
+
+#[test]
+fn test_directive_prologue() {
+    // Only a string literal statement at the start of the program is a directive.
+    for input in ["a; 'use strict'; let static = 1", "'use\\x20strict'; let static = 1", "'use strict' + 1; let static = 1"] {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        check_parser_errors(&mut parser);
+    }
+
+    let lexer = lexer::Lexer::new("'a'; \"use strict\"; let static = 1");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors().len(), 1);
+}
+
+#[test]
+fn test_variable_declarations() {
+    let input = "let a = 1 + 2, b; const c = `x`; var d, let = -1\nvar e = f\nreturn;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "let a = (1 + 2), b;",
+        "const c = `x`;",
+        "var d, let = (-1);",
+        "var e = f;",
+        "return;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_variable_declaration_errors() {
+    let input = "const a = 1, b; let let; var c = ;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("missing initializer in const declaration", Span::new(13, 14)),
+        ("'let' cannot be a lexically bound name", Span::new(20, 23)),
+        ("no prefix parse function for Semicolon found", Span::new(33, 34)),
+    ]);
+}
+
+#[test]
+fn test_boolean_and_null_literals() {
+    let input = "true; false; null; !true == false; undefined;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    // `undefined` is an ordinary identifier.
+    test_statements(&program, &["true;", "false;", "null;", "((!true) == false);", "undefined;"]);
+}
+
+#[test]
+fn test_grouped_expression() {
+    let input = r"
+(1 + 2) * 3;
+a + (b + c) + d;
+-(5 + 5);
+!(true == true);
+2 ** (3 ** 2);
+((a));
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "(((1 + 2)) * 3);",
+        "((a + ((b + c))) + d);",
+        "(-((5 + 5)));",
+        "(!((true == true)));",
+        "(2 ** ((3 ** 2)));",
+        "((a));",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_unclosed_group() {
+    let input = "(1 + 2; ()";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(errors, [
+        "expected next token to be RParen, got Semicolon instead",
+        "no prefix parse function for RParen found",
+    ]);
+}
+
+#[test]
+fn test_block_statement() {
+    let input = "{ let a = 1; { a } } {} { b\n}";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    test_statements(&program, &["{ let a = 1; { a; } }", "{}", "{ b; }"]);
+}
+
+#[test]
+fn test_if_statement() {
+    let input = r"
+if (x < y) { x }
+if (x) y; else z;
+if (a) b
+else if (c) d
+else { e }
+if (a) if (b) c; else d;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "if ((x < y)) { x; }",
+        "if (x) y; else z;",
+        "if (a) b; else if (c) d; else { e; }",
+        // The else belongs to the inner if.
+        "if (a) if (b) c; else d;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_if_statement_errors() {
+    let input = "if (a) let b = 1; if (a) const c = 1; if (a) let; if (a) {} else let d; if (a) b else c";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| (e.message.as_str(), e.span))
+        .collect();
+    assert_eq!(errors, [
+        ("lexical declaration cannot appear in a single-statement context", Span::new(7, 10)),
+        ("lexical declaration cannot appear in a single-statement context", Span::new(25, 30)),
+        ("lexical declaration cannot appear in a single-statement context", Span::new(65, 68)),
+        ("expected next token to be Semicolon, got Else instead", Span::new(81, 85)),
+    ]);
+}
+
+#[test]
+fn test_block_scopes() {
+    let input = r"
+let a; var b;
+{ let a; var c; { const c = 1; } }
+{ let b; }
+let a;
+var b, c;
+{ let d; { var d; } }
+let c;
+{ var e; } { let e; }
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter()
+        .map(|e| e.message.as_str())
+        .collect();
+    assert_eq!(errors, [
+        "'a' has already been declared",
+        "'d' has already been declared",
+        "'c' has already been declared",
+    ]);
+
+    let lexer = lexer::Lexer::new("{ let x, y; const z = 1; var w; }");
+    let mut parser = Parser::new(lexer);
+    let block = parser.parse_block().unwrap();
+    check_parser_errors(&mut parser);
+    assert_eq!(block.lexical_names, ["x", "y", "z"]);
+}
+
+#[test]
+fn test_unclosed_block() {
+    let input = "{ a; { b; }";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+
+    let errors: Vec<_> = parser.errors().iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(errors, ["expected next token to be RBrace, got EOF instead"]);
+}
+
+#[test]
+fn test_function_declaration() {
+    let input = r"
+function add(a, b) { return a + b; }
+function noop() {}
+function defaults(a, b = a * 2, ...rest) { let c = 1; c }
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "function add(a, b) { return (a + b); }",
+        "function noop() {}",
+        "function defaults(a, b = (a * 2), ...rest) { let c = 1; c; }",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_function_expression() {
+    let input = "let f = function(x,) { return x; }; (function named() {}); let g = function (...a) {}";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "let f = function(x) { return x; };",
+        "(function named() {});",
+        "let g = function(...a) {};",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_call_expression() {
+    let input = r"
+add(1, 2 * 3, 4 + 5);
+f();
+f(a,);
+f(...args, b, ...g(c));
+a + f(b) * c;
+f(a)(b);
+f
+(b);
+(function() {})();
+-f(a);
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "add(1, (2 * 3), (4 + 5));",
+        "f();",
+        "f(a);",
+        "f(...args, b, ...g(c));",
+        "(a + (f(b) * c));",
+        "f(a)(b);",
+        // No semicolon is inserted before a `(`.
+        "f(b);",
+        "(function() {})();",
+        "(-f(a));",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_function_errors() {
+    let tests = [
+        ("function () {}", "expected next token to be Identifier, got LParen instead"),
+        ("function f(a, ...b, c) {}", "rest parameter must be last formal parameter"),
+        ("function f(a, a = 1) {}", "duplicate parameter name not allowed in this context"),
+        ("function f(a, a) { 'use strict'; }", "duplicate parameter name not allowed in this context"),
+        ("function f(a) { let a; }", "'a' has already been declared"),
+        ("let f; function f() {}", "'f' has already been declared"),
+        ("{ function f() {} var f; }", "'f' has already been declared"),
+        ("f(a b);", "expected next token to be Commas, got Identifier instead"),
+        ("f(,);", "no prefix parse function for Commas found"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+
+    // Sloppy mode allows duplicate names in a plain parameter list, and `var` is
+    // scoped to its function.
+    let input = "function f(a, a) {} function g() { var x; { let y; } } let x; var f, y;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    check_parser_errors(&mut parser);
+}
+
+#[test]
+fn test_arrow_function() {
+    let input = r"
+x => x * 2;
+(a, b) => { return a + b; };
+() => (1);
+(a, ...rest,) => rest;
+f(x => x, (y) => y);
+async x => x;
+async (a, b) => {};
+async () => 1;
+async => async;
+async(a, ...b);
+async;
+(a, b);
+x => y => x + y;
+(a, b = a + 1) => b;
+async (a = 1) => a;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "(x) => (x * 2);",
+        "(a, b) => { return (a + b); };",
+        "() => (1);",
+        "(a, ...rest) => rest;",
+        "f((x) => x, (y) => y);",
+        "async (x) => x;",
+        "async (a, b) => {};",
+        "async () => 1;",
+        "(async) => async;",
+        "async(a, ...b);",
+        "async;",
+        "(a, b);",
+        "(x) => (y) => (x + y);",
+        "(a, b = (a + 1)) => b;",
+        "async (a = 1) => a;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_arrow_function_errors() {
+    let tests = [
+        ("(a + 1) => x", "malformed arrow function parameter list"),
+        ("(a, (b)) => x", "malformed arrow function parameter list"),
+        ("(a += 1) => x", "malformed arrow function parameter list"),
+        ("(...a = 1) => x", "malformed arrow function parameter list"),
+        ("(...a, b) => x", "rest parameter must be last formal parameter"),
+        ("(a, a) => x", "duplicate parameter name not allowed in this context"),
+        ("(a)\n=> x", "line terminator not permitted before arrow"),
+        ("(a, b) => { let a; }", "'a' has already been declared"),
+        ("(...a)", "expected next token to be Arrow, got EOF instead"),
+        ("(a,);", "expected next token to be Arrow, got Semicolon instead"),
+        ("async x y", "expected next token to be Arrow, got Identifier instead"),
+        // An arrow function ends its expression; only a `,` can follow it.
+        ("x => {}(1)", "expected next token to be Semicolon, got LParen instead"),
+        ("() => {}[1, 2]", "expected next token to be Semicolon, got LBracket instead"),
+        ("x => {}.a", "expected next token to be Semicolon, got Dot instead"),
+        ("x => {} + 1", "expected next token to be Semicolon, got Plus instead"),
+        ("x => {} ? a : b", "expected next token to be Semicolon, got Question instead"),
+        ("x => {} = 1", "expected next token to be Semicolon, got Assign instead"),
+        ("1 + x => x", "malformed arrow function parameter list"),
+        ("!x => x", "malformed arrow function parameter list"),
+        ("a || (b) => b", "malformed arrow function parameter list"),
+        ("a ?? async x => x", "malformed arrow function parameter list"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+
+    let lexer = lexer::Lexer::new("(a, b + 1) => x");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].span, Span::new(4, 9));
+
+    let lexer = lexer::Lexer::new("1 + x => x");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].span, Span::new(4, 10));
+
+    // After a block body a line break ends the statement, as `[` can't continue it.
+    let input = "const f = () => {}\n[1, 2].forEach(g)\nx => {}\n(1)\ng(x => x, y)";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+    test_statements(&program, &[
+        "const f = () => {};",
+        "[1, 2].forEach(g);",
+        "(x) => {};",
+        "(1);",
+        "g((x) => x, y);",
+    ]);
+}
+
+#[test]
+fn test_object_literal() {
+    let input = r"
+({});
+({a: 1, 'b': 2, 3: c, [d + 1]: e,});
+({a, get, set, async});
+({f(x) { return x; }, get g() { return 1; }, set g(v) {}, get: 1, set() {}});
+({if: 1, class: 2, ...rest, ...f()});
+() => ({});
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "({});",
+        "({a: 1, \"b\": 2, 3: c, [(d + 1)]: e});",
+        "({a, get, set, async});",
+        "({f(x) { return x; }, get g() { return 1; }, set g(v) {}, get: 1, set() {}});",
+        "({if: 1, class: 2, ...rest, ...f()});",
+        "() => ({});",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_member_expression() {
+    let input = r"
+a.b.c;
+a[b][c + 1];
+a.if.class;
+a.b(c).d[e]();
+a
+.b;
+-a.b;
+a?.b;
+a?.[b];
+a?.(b)?.c.d;
+a?.b[c]?.(d);
+f()`x`;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "a.b.c;",
+        "a[b][(c + 1)];",
+        "a.if.class;",
+        "a.b(c).d[e]();",
+        "a.b;",
+        "(-a.b);",
+        "a?.b;",
+        "a?.[b];",
+        "a?.(b)?.c.d;",
+        "a?.b[c]?.(d);",
+        "f()`x`;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_object_and_member_errors() {
+    let tests = [
+        ("({get a(b) {}})", "getter must not have any formal parameters"),
+        ("({set a() {}})", "setter must have exactly one formal parameter"),
+        ("({set a(...b) {}})", "setter must have exactly one formal parameter"),
+        ("({if})", "expected next token to be Colon, got RBrace instead"),
+        ("({a b})", "expected next token to be RBrace, got Identifier instead"),
+        ("({,})", "expected property name, got Commas instead"),
+        ("a.+", "expected next token to be Identifier, got Plus instead"),
+        ("a?.b`x`", "tagged template cannot be used in optional chain"),
+        ("a?.b.c`x`", "tagged template cannot be used in optional chain"),
+        ("a[b", "expected next token to be RBracket, got EOF instead"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+
+    // Parentheses end the chain.
+    let lexer = lexer::Lexer::new("(a?.b)`x`");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    check_parser_errors(&mut parser);
+}
+
+#[test]
+fn test_array_literal() {
+    let input = "[]; [1, 2 * 2, 3 + 3]; [1,,3]; [,]; [1,]; [1,,]; [,,a]; [...a, b, ...[c]];";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "[];",
+        "[1, (2 * 2), (3 + 3)];",
+        "[1, , 3];",
+        "[,];",
+        "[1];",
+        "[1, ,];",
+        "[, , a];",
+        "[...a, b, ...[c]];",
+    ];
+    test_statements(&program, &expects);
+
+    let lexer = lexer::Lexer::new("[1 2]");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].message, "expected next token to be RBracket, got Number instead");
+}
+
+#[test]
+fn test_call_and_member_precedences() {
+    let input = r"
+a * [1, 2, 3, 4][b * c] * d;
+add(a * b[2], b[1], 2 * [1, 2][1]);
+a.b()[c].d();
+-a[0];
+!f(a)[0](b);
+a + b.c * d(e);
+a ** b.c;
+[f][0]().g;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "((a * [1, 2, 3, 4][(b * c)]) * d);",
+        "add((a * b[2]), b[1], (2 * [1, 2][1]));",
+        "a.b()[c].d();",
+        "(-a[0]);",
+        "(!f(a)[0](b));",
+        "(a + (b.c * d(e)));",
+        "(a ** b.c);",
+        "[f][0]().g;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_loop_statements() {
+    let input = r"
+while (a < b) { a; }
+do a; while (b)
+do { b } while (c); x
+for (;;) {}
+for (let i = 0, j; i < n; f(i)) g(i);
+for (var k = 1; ; ) ;
+for (a; b; c) d
+for (let x in o) {}
+for (const x of xs) f(x);
+for (a.b of c) {}
+for (let of of xs) {}
+for (let.a in b) ;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "while ((a < b)) { a; }",
+        "do a; while (b);",
+        "do { b; } while (c);",
+        "x;",
+        "for (;;) {}",
+        "for (let i = 0, j; (i < n); f(i)) g(i);",
+        "for (var k = 1;;) ;",
+        "for (a; b; c) d;",
+        "for (let x in o) {}",
+        "for (const x of xs) f(x);",
+        "for (a.b of c) {}",
+        "for (let of of xs) {}",
+        "for (let.a in b) ;",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_break_and_continue() {
+    let input = r"
+while (a) { break; }
+for (;;) continue
+outer: for (;;) { inner: while (b) { continue outer; break inner; } }
+block: { break block; }
+a: b: while (c) continue a;
+while (a) { break
+label; }
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "while (a) { break; }",
+        "for (;;) continue;",
+        "outer: for (;;) { inner: while (b) { continue outer; break inner; } }",
+        "block: { break block; }",
+        "a: b: while (c) continue a;",
+        // A line break after `break` ends the statement.
+        "while (a) { break; label; }",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_loop_errors() {
+    let tests = [
+        ("break;", "illegal break statement"),
+        ("{ break; }", "illegal break statement"),
+        ("continue;", "illegal continue statement: no surrounding iteration statement"),
+        ("a: { continue; }", "illegal continue statement: no surrounding iteration statement"),
+        ("while (a) break b;", "undefined label 'b'"),
+        ("a: { continue a; }", "illegal continue statement: 'a' does not denote an iteration statement"),
+        ("a: while (b) { c: { continue c; } }", "illegal continue statement: 'c' does not denote an iteration statement"),
+        ("a: { a: ; }", "label 'a' has already been declared"),
+        ("a: ; while (b) break a;", "undefined label 'a'"),
+        ("while (a) { function f() { break; } }", "illegal break statement"),
+        ("a: while (b) { () => { break a; }; }", "undefined label 'a'"),
+        ("while (a) let b;", "lexical declaration cannot appear in a single-statement context"),
+        ("for (const a; b; c) ;", "missing initializer in const declaration"),
+        ("for (let a, b of c) ;", "only one variable can be declared in a for-of loop"),
+        ("for (var a = 1 in b) ;", "for-in loop variable declaration may not have an initializer"),
+        ("for (f() in b) ;", "invalid left-hand side in for-in loop"),
+        ("for (let a; ; ) { let a; var a; }", "'a' has already been declared"),
+        ("do a while (b)", "expected next token to be Semicolon, got While instead"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+
+    // Errors about the loop variable cover the whole of it.
+    let lexer = lexer::Lexer::new("for (var a = 1, b in c) ;");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].span, Span::new(5, 17));
+
+    // Labels are scoped to their statement, and a loop header has its own scope.
+    let input = "a: ; a: while (b) break a; for (let i;;) {} for (let i;;) {} let i;";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    check_parser_errors(&mut parser);
+}
+
+#[test]
+fn test_assignment_expression() {
+    let input = r"
+a = 1;
+a = b = c + 1;
+a.b += 2 * c;
+a[0] **= 2;
+x -= 1; x *= 1; x /= 1; x %= 1;
+x <<= 1; x >>= 1; x >>>= 1;
+x &= 1; x |= 1; x ^= 1;
+x &&= y; x ||= y; x ??= y;
+(a) = 1;
+[a, b] = c;
+({a} = b);
+[a = 1, [b.c], , ...d] = e;
+({a, b: [c], d: e = 1, 'f': g[0], ...h} = i);
+for ([a, {b}] of c) ;
+f(a = 1);
+({a = 1} = b);
+for ({a = 1} of b) ;
+[{a = 1, b: {c = d = 2}}] = e;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "(a = 1);",
+        "(a = (b = (c + 1)));",
+        "(a.b += (2 * c));",
+        "(a[0] **= 2);",
+        "(x -= 1);", "(x *= 1);", "(x /= 1);", "(x %= 1);",
+        "(x <<= 1);", "(x >>= 1);", "(x >>>= 1);",
+        "(x &= 1);", "(x |= 1);", "(x ^= 1);",
+        "(x &&= y);", "(x ||= y);", "(x ??= y);",
+        "((a) = 1);",
+        "([a, b] = c);",
+        "(({a} = b));",
+        "([(a = 1), [b.c], , ...d] = e);",
+        "(({a, b: [c], d: (e = 1), \"f\": g[0], ...h} = i));",
+        "for ([a, {b}] of c) ;",
+        "f((a = 1));",
+        "(({a = 1} = b));",
+        "for ({a = 1} of b) ;",
+        "([{a = 1, b: {c = (d = 2)}}] = e);",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_update_expression() {
+    let input = r"
+++a;
+a--;
+-a++;
+++a.b[c];
+a.b++ + --c;
+a
+++b
+for (i = 0; i < n; i++) {}
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "(++a);",
+        "(a--);",
+        "(-(a++));",
+        "(++a.b[c]);",
+        "((a.b++) + (--c));",
+        // `++` on a new line belongs to the next statement.
+        "a;",
+        "(++b);",
+        "for ((i = 0); (i < n); (i++)) {}",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_assignment_target_errors() {
+    let tests = [
+        ("1 = 2;", "invalid left-hand side in assignment", Span::new(0, 1)),
+        ("a + b = c;", "invalid left-hand side in assignment", Span::new(0, 5)),
+        ("x; f() = 1;", "invalid left-hand side in assignment", Span::new(3, 6)),
+        ("a?.b = 1;", "invalid left-hand side in assignment", Span::new(0, 4)),
+        ("({a}) = 1;", "invalid left-hand side in assignment", Span::new(0, 5)),
+        ("(a, b) = 1;", "invalid left-hand side in assignment", Span::new(0, 6)),
+        ("[a] += 1;", "invalid left-hand side in assignment", Span::new(0, 3)),
+        ("a = 1 = 2;", "invalid left-hand side in assignment", Span::new(4, 5)),
+        ("++1;", "invalid left-hand side expression in prefix operation", Span::new(2, 3)),
+        ("--(a + b);", "invalid left-hand side expression in prefix operation", Span::new(2, 9)),
+        ("f()++;", "invalid left-hand side expression in postfix operation", Span::new(0, 3)),
+        ("a++ ++;", "invalid left-hand side expression in postfix operation", Span::new(0, 3)),
+        ("'use strict'; eval = 1;", "unexpected eval or arguments in strict mode", Span::new(14, 18)),
+        ("'use strict'; arguments++;", "unexpected eval or arguments in strict mode", Span::new(14, 23)),
+        ("for (f() of a) ;", "invalid left-hand side in for-of loop", Span::new(5, 8)),
+        // Patterns point at the element that can't be assigned to.
+        ("[1] = x;", "invalid left-hand side in assignment", Span::new(1, 2)),
+        ("[[a, 1]] = b;", "invalid left-hand side in assignment", Span::new(5, 6)),
+        ("[a = 1, b + 1] = c;", "invalid left-hand side in assignment", Span::new(8, 13)),
+        ("[...a, b] = c;", "invalid left-hand side in assignment", Span::new(1, 5)),
+        ("[...a = 1] = b;", "invalid left-hand side in assignment", Span::new(1, 9)),
+        ("[(a = 1)] = b;", "invalid left-hand side in assignment", Span::new(1, 8)),
+        ("({a: 1} = x);", "invalid left-hand side in assignment", Span::new(2, 6)),
+        ("({a() {}} = x);", "invalid left-hand side in assignment", Span::new(2, 8)),
+        ("({get a() {}} = x);", "invalid left-hand side in assignment", Span::new(2, 12)),
+        ("({...a, b} = c);", "invalid left-hand side in assignment", Span::new(2, 6)),
+        ("({...[a]} = c);", "invalid left-hand side in assignment", Span::new(2, 8)),
+        ("for ([1] of x) ;", "invalid left-hand side in for-of loop", Span::new(6, 7)),
+        ("for ({a: f()} in x) ;", "invalid left-hand side in for-in loop", Span::new(6, 12)),
+        // A shorthand initializer is only allowed once its object becomes a pattern.
+        ("({a = 1});", "invalid shorthand property initializer", Span::new(2, 7)),
+        ("f({a = 1});", "invalid shorthand property initializer", Span::new(3, 8)),
+        ("({a = 1}).b = c;", "invalid shorthand property initializer", Span::new(2, 7)),
+        ("({b: {a = 1}.c} = d);", "invalid shorthand property initializer", Span::new(6, 11)),
+        ("({a = {b = 1}} = c);", "invalid shorthand property initializer", Span::new(7, 12)),
+        ("for ({a = 1};;) ;", "invalid shorthand property initializer", Span::new(6, 11)),
+        ("({'a' = 1});", "expected next token to be Colon, got Assign instead", Span::new(6, 7)),
+    ];
+    for (input, expect, span) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        let error = &parser.errors()[0];
+        assert_eq!((error.message.as_str(), error.span), (expect, span), "{}", input);
+    }
+}
+
+#[test]
+fn test_logical_and_conditional_expressions() {
+    let input = r"
+a && b || c && d;
+a ?? b ?? c;
+(a || b) ?? c;
+a ?? (b && c);
+a ? b : c;
+a ? b : c ? d : e;
+a ? b ? c : d : e;
+a || b ? c + 1 : d;
+x = a ? b : c;
+a ? b = 1 : c = 2;
+a ? (b, c) : d;
+";
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+
+    let expects = [
+        "((a && b) || (c && d));",
+        "((a ?? b) ?? c);",
+        "(((a || b)) ?? c);",
+        "(a ?? ((b && c)));",
+        "(a ? b : c);",
+        "(a ? b : (c ? d : e));",
+        "(a ? (b ? c : d) : e);",
+        "((a || b) ? (c + 1) : d);",
+        "(x = (a ? b : c));",
+        "(a ? (b = 1) : (c = 2));",
+        "(a ? (b, c) : d);",
+    ];
+    test_statements(&program, &expects);
+}
+
+#[test]
+fn test_logical_and_conditional_errors() {
+    let tests = [
+        ("a ?? b || c;", Span::new(7, 9)),
+        ("a || b ?? c;", Span::new(7, 9)),
+        ("a && b ?? c;", Span::new(7, 9)),
+        ("a ?? b && c;", Span::new(2, 4)),
+    ];
+    for (input, span) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        let error = &parser.errors()[0];
+        assert_eq!(error.message, "'??' cannot be mixed with '&&' or '||' without parentheses", "{}", input);
+        assert_eq!(error.span, span, "{}", input);
+    }
+
+    let tests = [
+        ("a ? b;", "expected next token to be Colon, got Semicolon instead"),
+        ("a ? : c;", "no prefix parse function for Colon found"),
+        ("a ? b : ;", "no prefix parse function for Semicolon found"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+}
+
+/// Parses `input` as a single statement with no errors and prints it.
+fn print_statement(input: &str) -> String {
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{}: {}", input, parser.errors()[0]);
+    assert_eq!(program.statements.len(), 1, "{}", input);
+    return program.statements[0].to_string();
+}
+
+#[test]
+fn test_binary_operator_precedence_table() {
+    // ECMAScript binary operators from the loosest to the tightest binding.
+    let levels: &[&[&str]] = &[
+        &["||"],
+        &["&&"],
+        &["|"],
+        &["^"],
+        &["&"],
+        &["==", "!=", "===", "!=="],
+        &["<", ">", "<=", ">=", "instanceof", "in"],
+        &["<<", ">>", ">>>"],
+        &["+", "-"],
+        &["*", "/", "%"],
+        &["**"],
+    ];
+    for (index, level) in levels.iter().enumerate() {
+        for op in level.iter() {
+            // Only `**` is right-associative, and operators on one level associate
+            // like a single one.
+            for other in level.iter() {
+                let expect = if *op == "**" {
+                    format!("(a {} (b {} c));", op, other)
+                } else {
+                    format!("((a {} b) {} c);", op, other)
+                };
+                assert_eq!(print_statement(&format!("a {} b {} c", op, other)), expect);
+            }
+            for tighter in levels[index + 1..].iter().flat_map(|level| level.iter()) {
+                let input = format!("a {} b {} c", op, tighter);
+                assert_eq!(print_statement(&input), format!("(a {} (b {} c));", op, tighter));
+                let input = format!("a {} b {} c", tighter, op);
+                assert_eq!(print_statement(&input), format!("((a {} b) {} c);", tighter, op));
+            }
+        }
+    }
+
+    // `??` sits with `||` but only mixes with tighter operators.
+    assert_eq!(print_statement("a ?? b ?? c"), "((a ?? b) ?? c);");
+    for tighter in levels[2..].iter().flat_map(|level| level.iter()) {
+        assert_eq!(print_statement(&format!("a ?? b {} c", tighter)), format!("(a ?? (b {} c));", tighter));
+        assert_eq!(print_statement(&format!("a {} b ?? c", tighter)), format!("((a {} b) ?? c);", tighter));
+    }
+}
+
+#[test]
+fn test_operator_precedence_levels() {
+    let tests = [
+        // Comma, assignment and conditional are looser than any binary operator.
+        ("a, b, c", "a, b, c;"),
+        ("a = b, c", "(a = b), c;"),
+        ("a, b = c", "a, (b = c);"),
+        ("a += b -= c", "(a += (b -= c));"),
+        ("a = b || c", "(a = (b || c));"),
+        ("a ? b : c, d", "(a ? b : c), d;"),
+        ("a = b ? c : d", "(a = (b ? c : d));"),
+        ("a ? b : c = d", "(a ? b : (c = d));"),
+        ("a ?? b ? c : d", "((a ?? b) ? c : d);"),
+        ("x => a, b", "(x) => a, b;"),
+        ("f(a, b = c)", "f(a, (b = c));"),
+        ("[a, (b, c)]", "[a, (b, c)];"),
+        ("a[b, c]", "a[b, c];"),
+        // Unary operators bind tighter than `**` on its right, but not on its left.
+        ("-a * b", "((-a) * b);"),
+        ("a ** -b", "(a ** (-b));"),
+        ("(-a) ** b", "(((-a)) ** b);"),
+        ("-(a ** b)", "(-((a ** b)));"),
+        ("++a ** b", "((++a) ** b);"),
+        ("a-- ** b", "((a--) ** b);"),
+        ("!a in b", "((!a) in b);"),
+        ("a.b ** c[d]", "(a.b ** c[d]);"),
+    ];
+    for (input, expect) in tests {
+        assert_eq!(print_statement(input), expect, "{}", input);
+    }
+
+    let tests = [
+        ("-a ** b;", Span::new(0, 2)),
+        ("!a ** b;", Span::new(0, 2)),
+        ("a ** -b ** c;", Span::new(5, 7)),
+        ("x * -f(y) ** 2;", Span::new(4, 9)),
+    ];
+    for (input, span) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        let error = &parser.errors()[0];
+        assert_eq!(error.message, "unary operator used immediately before exponentiation expression", "{}", input);
+        assert_eq!(error.span, span, "{}", input);
+    }
+}
+
+#[test]
+fn test_in_operator_in_for_head() {
+    let tests = [
+        ("for (a in b) ;", "for (a in b) ;"),
+        ("for (a in b in c) ;", "for (a in (b in c)) ;"),
+        ("for (var a = (b in c); a; ) ;", "for (var a = ((b in c)); a;) ;"),
+        ("for (a = [b in c];;) ;", "for ((a = [(b in c)]);;) ;"),
+        ("for (f(a in b);;) ;", "for (f((a in b));;) ;"),
+        ("for (a ? b in c : d;;) ;", "for ((a ? (b in c) : d);;) ;"),
+        ("for (a = function () { return b in c; };;) ;", "for ((a = function() { return (b in c); });;) ;"),
+        ("for (;a in b;) ;", "for (; (a in b);) ;"),
+        ("for (a of b = c) ;", "for (a of (b = c)) ;"),
+    ];
+    for (input, expect) in tests {
+        assert_eq!(print_statement(input), expect, "{}", input);
+    }
+
+    let tests = [
+        ("for (let a = b in c) ;", "for-in loop variable declaration may not have an initializer"),
+        ("for (a, b in c) ;", "invalid left-hand side in for-in loop"),
+        ("for (a + b in c) ;", "invalid left-hand side in for-in loop"),
+        ("for (a of b, c) ;", "expected next token to be RParen, got Commas instead"),
+    ];
+    for (input, expect) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert_eq!(parser.errors()[0].message, expect, "{}", input);
+    }
+}
+
+#[test]
+fn test_await_expression() {
+    let tests = [
+        ("async () => await a;", "async () => (await a);"),
+        ("async x => { await f(x); };", "async (x) => { (await f(x)); };"),
+        ("async () => await a + await b;", "async () => ((await a) + (await b));"),
+        ("async () => () => await;", "async () => () => await;"),
+        ("async () => function () { await; };", "async () => function() { await; };"),
+        ("async function f() { await x; }", "async function f() { (await x); }"),
+        ("f = async function () { return await g(); };", "(f = async function() { return (await g()); });"),
+        ("async function f() { return () => await; }", "async function f() { return () => await; }"),
+        ("async function f() { function g() { await; } }", "async function f() { function g() { await; } }"),
+        ("({ async foo() { await x; } });", "({async foo() { (await x); }});"),
+        ("({ async 'a'() {}, async [b]() {}, async: 1, async() { await; } });",
+            "({async \"a\"() {}, async [b]() {}, async: 1, async() { await; }});"),
+        // Outside an async function `await` is an identifier.
+        ("await;", "await;"),
+        ("await + 1;", "(await + 1);"),
+        ("() => await;", "() => await;"),
+    ];
+    for (input, expect) in tests {
+        assert_eq!(print_statement(input), expect, "{}", input);
+    }
+
+    // A line break after `async` leaves it an identifier.
+    let lexer = lexer::Lexer::new("async\nfunction f() { await; }");
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    check_parser_errors(&mut parser);
+    test_statements(&program, &["async;", "function f() { await; }"]);
+
+    // Also in an object literal, where it is then a shorthand property.
+    let lexer = lexer::Lexer::new("({ async\nfoo() {} });");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].message, "expected next token to be RBrace, got Identifier instead");
+
+    let lexer = lexer::Lexer::new("async () => await a ** 2;");
+    let mut parser = Parser::new(lexer);
+    parser.parse_program();
+    assert_eq!(parser.errors()[0].message, "unary operator used immediately before exponentiation expression");
+}
+
+#[test]
+fn test_strict_mode_delete() {
+    let tests = [
+        ("'use strict'; delete a;", Span::new(21, 22)),
+        ("'use strict'; delete (a);", Span::new(21, 24)),
+        ("function f() { 'use strict'; delete x; }", Span::new(36, 37)),
+    ];
+    for (input, span) in tests {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        let error = &parser.errors()[0];
+        assert_eq!(error.message, "delete of an unqualified identifier in strict mode", "{}", input);
+        assert_eq!(error.span, span, "{}", input);
+    }
+
+    // Properties can be deleted in strict mode, and identifiers outside it.
+    for input in ["'use strict'; delete a.b; delete a[0]; delete f();", "function g() { delete x; }"] {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        check_parser_errors(&mut parser);
+    }
+}
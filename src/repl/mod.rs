@@ -1,6 +1,6 @@
 use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use crate::lexer::Lexer;
-use crate::lexer::token::TokenKind;
+use crate::lexer::span::LineIndex;
 
 const PROMPT: &str = ">> ";
 
@@ -12,7 +12,7 @@ pub fn start<R, W>(reader: &mut R, writer: &mut W)
     let mut buf_writer = BufWriter::new(writer);
     let mut line = String::new();
     loop {
-        buf_writer.write(PROMPT.as_bytes())?;
+        buf_writer.write_all(PROMPT.as_bytes())?;
         buf_writer.flush()?;
         line.clear();
         buf_reader.read_line(&mut line)?;
@@ -24,12 +24,12 @@ pub fn start<R, W>(reader: &mut R, writer: &mut W)
             continue;
         }
         let mut lexer = Lexer::new(&line);
-        loop {
-            let token = lexer.next_token();
-            if token.kind == TokenKind::EOF {
-                break;
-            }
-            write!(buf_writer, "{:?}\n", token)?;
+        for token in lexer.by_ref() {
+            writeln!(buf_writer, "{:?}", token)?;
+        }
+        let index = LineIndex::new(&line);
+        for e in lexer.errors() {
+            writeln!(buf_writer, "error: {}", e.describe(&index))?;
         }
         buf_writer.flush()?;
     }